@@ -0,0 +1,168 @@
+use std::env;
+use std::fs;
+use std::str::FromStr;
+
+/// How benchmark threads are assigned to CPUs.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Placement {
+    /// Let the scheduler migrate threads.
+    Unpinned,
+    /// Fill one socket first, SMT siblings next to each other.
+    Compact,
+    /// One thread per physical core, alternating sockets, before using SMT siblings.
+    Spread,
+}
+
+impl FromStr for Placement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Placement, String> {
+        match s {
+            "unpinned" => Ok(Placement::Unpinned),
+            "compact" => Ok(Placement::Compact),
+            "spread" => Ok(Placement::Spread),
+            _ => Err(format!("unknown placement: {}", s)),
+        }
+    }
+}
+
+struct Cpu {
+    id: usize,
+    package: usize,
+    core: usize,
+}
+
+fn read_topology(cpu: usize, name: &str) -> Option<usize> {
+    let path = format!("/sys/devices/system/cpu/cpu{}/topology/{}", cpu, name);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn cpus() -> Vec<Cpu> {
+    allowed_cpus()
+        .into_iter()
+        .map(|id| Cpu {
+            id,
+            package: read_topology(id, "physical_package_id").unwrap_or(0),
+            core: read_topology(id, "core_id").unwrap_or(id),
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Vec<usize> {
+    use std::io;
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        let r = libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set);
+        assert_eq!(
+            r,
+            0,
+            "sched_getaffinity failed: {}",
+            io::Error::last_os_error()
+        );
+        (0..libc::CPU_SETSIZE as usize)
+            .filter(|&cpu| libc::CPU_ISSET(cpu, &set))
+            .collect()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cpus() -> Vec<usize> {
+    Vec::new()
+}
+
+/// CPUs in the order threads should be placed on them,
+/// empty for [`Placement::Unpinned`].
+pub fn cpu_order(placement: Placement) -> Vec<usize> {
+    if placement == Placement::Unpinned {
+        return Vec::new();
+    }
+    let mut cpus = cpus();
+    cpus.sort_by_key(|c| (c.package, c.core, c.id));
+    if placement == Placement::Compact {
+        return cpus.into_iter().map(|c| c.id).collect();
+    }
+
+    // Sort by rank among SMT siblings, then by rank of the core within its package.
+    let mut keys = Vec::new();
+    let mut sibling = 0;
+    let mut core_rank = 0;
+    for (i, c) in cpus.iter().enumerate() {
+        if i == 0 || cpus[i - 1].package != c.package {
+            sibling = 0;
+            core_rank = 0;
+        } else if cpus[i - 1].core != c.core {
+            sibling = 0;
+            core_rank += 1;
+        } else {
+            sibling += 1;
+        }
+        keys.push((sibling, core_rank, c.package, c.id));
+    }
+    keys.sort();
+    keys.into_iter().map(|(_, _, _, id)| id).collect()
+}
+
+/// Panic if pinning `n_threads` threads to `order` would put several threads on one CPU,
+/// unless `BENCH_OVERSUBSCRIBE=1` is set, in which case only warn.
+pub fn check_oversubscription(order: &[usize], n_threads: usize) {
+    if order.is_empty() || n_threads <= order.len() {
+        return;
+    }
+    let message = format!(
+        "{} threads pinned to {} CPUs, threads will share CPUs",
+        n_threads,
+        order.len()
+    );
+    if env::var("BENCH_OVERSUBSCRIBE").as_deref() == Ok("1") {
+        eprintln!("WARNING: {}", message);
+    } else {
+        panic!("{}, set BENCH_OVERSUBSCRIBE=1 to allow", message);
+    }
+}
+
+/// CPU for thread `i` according to the order returned by [`cpu_order`],
+/// wrapping around when there are more threads than CPUs, see [`check_oversubscription`].
+pub fn cpu_for_thread(order: &[usize], i: usize) -> Option<usize> {
+    if order.is_empty() {
+        None
+    } else {
+        Some(order[i % order.len()])
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn pin_current_thread(cpu: usize) {
+    use std::io;
+    use std::mem;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        let r = libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set);
+        assert_eq!(
+            r,
+            0,
+            "sched_setaffinity({}) failed: {}",
+            cpu,
+            io::Error::last_os_error()
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin_current_thread(_cpu: usize) {
+    panic!("CPU pinning is only supported on Linux");
+}
+
+/// Pin current thread to CPU from `BENCH_CPU` environment variable if it is set.
+pub fn pin_current_thread_from_env() -> Option<usize> {
+    let cpu = env::var("BENCH_CPU").ok()?;
+    let cpu = cpu
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid BENCH_CPU: {}", cpu));
+    pin_current_thread(cpu);
+    Some(cpu)
+}
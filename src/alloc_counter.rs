@@ -0,0 +1,90 @@
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::ops::Sub;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+/// Number of running [`count_allocations`] calls, nothing is counted while it is 0.
+static COUNTING: AtomicUsize = AtomicUsize::new(0);
+
+/// System allocator which counts allocations and reallocations.
+///
+/// Counters are only updated if a binary installs it,
+/// and only inside [`count_allocations`] so timed code pays for one relaxed load:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+pub struct CountingAllocator;
+
+#[inline]
+fn record(size: usize) {
+    if COUNTING.load(Ordering::Relaxed) == 0 {
+        return;
+    }
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AllocStats {
+    /// Allocations and reallocations.
+    pub allocations: u64,
+    /// Sum of requested sizes.
+    pub bytes: u64,
+}
+
+impl Sub for AllocStats {
+    type Output = AllocStats;
+
+    fn sub(self, rhs: AllocStats) -> AllocStats {
+        AllocStats {
+            allocations: self.allocations - rhs.allocations,
+            bytes: self.bytes - rhs.bytes,
+        }
+    }
+}
+
+/// Totals counted so far, on all threads.
+pub fn stats() -> AllocStats {
+    AllocStats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+/// Run `f` and return allocations it made, including other threads running concurrently.
+pub fn count_allocations<R>(f: impl FnOnce() -> R) -> (R, AllocStats) {
+    COUNTING.fetch_add(1, Ordering::SeqCst);
+    let before = stats();
+    let r = f();
+    let after = stats();
+    COUNTING.fetch_sub(1, Ordering::SeqCst);
+    (r, after - before)
+}
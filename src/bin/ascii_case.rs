@@ -0,0 +1,263 @@
+#![feature(portable_simd)]
+
+use std::cell::RefCell;
+use std::hint;
+use std::simd::u8x32;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::mixed_strings;
+use rust_str_bench::random_strings::random_mixed_case_string;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+const ONES: u64 = 0x0101_0101_0101_0101;
+
+#[inline(never)]
+fn to_ascii_lowercase(s: &str) -> String {
+    s.to_ascii_lowercase()
+}
+
+#[inline(never)]
+fn make_ascii_lowercase(s: &mut [u8]) {
+    s.make_ascii_lowercase()
+}
+
+/// Lowercase 8 bytes at once: a byte is uppercase if adding `0x80 - 'A'`
+/// sets its high bit and adding `0x80 - 'Z' - 1` does not.
+#[inline(never)]
+fn make_ascii_lowercase_swar(s: &mut [u8]) {
+    let mut chunks = s.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        let x = u64::from_ne_bytes(chunk.try_into().unwrap());
+        // Clear high bits so additions do not carry into the next byte.
+        let heptets = x & (ONES * 0x7f);
+        let ge_a = heptets + ONES * (0x80 - b'A' as u64);
+        let gt_z = heptets + ONES * (0x80 - b'Z' as u64 - 1);
+        let upper = (ge_a ^ gt_z) & !x & (ONES * 0x80);
+        chunk.copy_from_slice(&(x | (upper >> 2)).to_ne_bytes());
+    }
+    chunks.into_remainder().make_ascii_lowercase();
+}
+
+/// Same range check as SWAR, lanes do not need carry protection.
+#[inline(never)]
+fn make_ascii_lowercase_simd(s: &mut [u8]) {
+    type V = u8x32;
+    let mut chunks = s.chunks_exact_mut(V::LANES);
+    for chunk in &mut chunks {
+        let x = V::from_slice(chunk);
+        let ge_a = x + V::splat(0x80 - b'A');
+        let gt_z = x + V::splat(0x80 - b'Z' - 1);
+        let upper = (ge_a ^ gt_z) & !x & V::splat(0x80);
+        chunk.copy_from_slice((x | (upper >> V::splat(2))).as_array());
+    }
+    chunks.into_remainder().make_ascii_lowercase();
+}
+
+type MakeLowercase = fn(&mut [u8]);
+
+const MAKE_LOWERCASE_IMPLS: &[(&str, MakeLowercase)] = &[
+    ("make_ascii_lowercase", make_ascii_lowercase),
+    ("swar", make_ascii_lowercase_swar),
+    ("simd", make_ascii_lowercase_simd),
+];
+
+/// ASCII chars for which `char::is_whitespace` is true,
+/// unlike `u8::is_ascii_whitespace` this includes vertical tab.
+#[inline]
+fn is_ascii_white_space(b: u8) -> bool {
+    matches!(b, b'\t'..=b'\r' | b' ')
+}
+
+#[inline(never)]
+fn trim(s: &str) -> &str {
+    s.trim()
+}
+
+#[inline(never)]
+fn trim_start(s: &str) -> &str {
+    s.trim_start()
+}
+
+/// Scan ASCII bytes, fall back to `trim_start` at the first non-ASCII byte.
+#[inline(never)]
+fn trim_start_manual(s: &str) -> &str {
+    let start = s
+        .bytes()
+        .position(|b| !is_ascii_white_space(b))
+        .unwrap_or(s.len());
+    let rest = &s[start..];
+    match rest.as_bytes().first() {
+        Some(b) if !b.is_ascii() => rest.trim_start(),
+        _ => rest,
+    }
+}
+
+#[inline]
+fn trim_end_manual(s: &str) -> &str {
+    let end = s
+        .bytes()
+        .rposition(|b| !is_ascii_white_space(b))
+        .map_or(0, |i| i + 1);
+    let rest = &s[..end];
+    match rest.as_bytes().last() {
+        Some(b) if !b.is_ascii() => rest.trim_end(),
+        _ => rest,
+    }
+}
+
+#[inline(never)]
+fn trim_manual(s: &str) -> &str {
+    trim_end_manual(trim_start_manual(s))
+}
+
+type Trim = fn(&str) -> &str;
+
+const TRIM_IMPLS: &[(&str, Trim)] = &[("trim", trim), ("trim manual", trim_manual)];
+
+const TRIM_START_IMPLS: &[(&str, Trim)] = &[
+    ("trim_start", trim_start),
+    ("trim_start manual", trim_start_manual),
+];
+
+/// Whitespace and non-whitespace chars of different UTF-8 lengths.
+const TRIM_TEST_CHARS: &[char] = &[
+    ' ', '\t', '\n', '\x0b', '\x0c', '\r', '\x1c', '\u{85}', '\u{a0}', '\u{3000}', 'a', 'Ж', '中',
+];
+
+/// Offset and length of `trimmed` in `s`, offset of an empty result is not specified.
+fn subslice_range(s: &str, trimmed: &str) -> (usize, usize) {
+    if trimmed.is_empty() {
+        return (0, 0);
+    }
+    (
+        trimmed.as_ptr() as usize - s.as_ptr() as usize,
+        trimmed.len(),
+    )
+}
+
+fn test_trim(inputs: &[String], reference: Trim, impls: &[(&str, Trim)]) {
+    differential_test(
+        inputs,
+        |s| subslice_range(s, reference(s)),
+        impls,
+        |f, s| subslice_range(s, f(s)),
+    );
+}
+
+/// Byte-wise lowercase that does not use std's ASCII case conversions,
+/// so it can be the reference for them too.
+fn reference_lowercase(s: &str) -> String {
+    let bytes = s
+        .bytes()
+        .map(|b| if b.is_ascii_uppercase() { b | 0x20 } else { b })
+        .collect();
+    String::from_utf8(bytes).unwrap()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let inputs = mixed_strings(10_000, 70, random_mixed_case_string);
+    differential_test(
+        &inputs,
+        |s| reference_lowercase(s),
+        MAKE_LOWERCASE_IMPLS,
+        |f, s| {
+            let mut bytes = s.clone().into_bytes();
+            f(&mut bytes);
+            String::from_utf8(bytes).unwrap()
+        },
+    );
+    differential_test(
+        &inputs,
+        |s| reference_lowercase(s),
+        &[("to_ascii_lowercase", to_ascii_lowercase)],
+        |f, s| f(s),
+    );
+
+    let mut trim_inputs: Vec<String> = (0..10_000)
+        .map(|_| {
+            let len = rng.gen_range(0..=10);
+            (0..len)
+                .map(|_| TRIM_TEST_CHARS[rng.gen_range(0..TRIM_TEST_CHARS.len())])
+                .collect()
+        })
+        .collect();
+    trim_inputs.extend(gen_padded_strings());
+    test_trim(&trim_inputs, str::trim, TRIM_IMPLS);
+    test_trim(&trim_inputs, str::trim_start, TRIM_START_IMPLS);
+}
+
+/// Mixed-case strings with up to 4 ASCII whitespace chars on each side.
+fn gen_padded_strings() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let mut padding = || -> String {
+        (0..rng.gen_range(0..=4))
+            .map(|_| [' ', '\t', '\n'][rng.gen_range(0..3)])
+            .collect()
+    };
+    (0..1_000)
+        .map(|_| {
+            let len = rand::thread_rng().gen_range(0..=30);
+            padding() + &random_mixed_case_string(len) + &padding()
+        })
+        .collect()
+}
+
+fn main() {
+    test();
+
+    let mut rng = rand::thread_rng();
+    let corpora: Vec<(&str, Vec<String>)> = vec![
+        (
+            "lower",
+            (0..1_000)
+                .map(|_| random_string(rng.gen_range(0..=30)))
+                .collect(),
+        ),
+        (
+            "mixed",
+            (0..1_000)
+                .map(|_| random_mixed_case_string(rng.gen_range(0..=30)))
+                .collect(),
+        ),
+    ];
+    let padded = gen_padded_strings();
+    // In-place implementations lowercase a fresh copy on each call.
+    let buf = &RefCell::new(Vec::new());
+    let mut benchmarks = Vec::new();
+    for (corpus, strings) in &corpora {
+        benchmarks.push(Benchmark::new(
+            &format!("{} to_ascii_lowercase", corpus),
+            move || {
+                for s in strings {
+                    hint::black_box(to_ascii_lowercase(s));
+                }
+            },
+        ));
+        for &(name, f) in MAKE_LOWERCASE_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("{} copy + {}", corpus, name),
+                move || {
+                    let mut buf = buf.borrow_mut();
+                    for s in strings {
+                        buf.clear();
+                        buf.extend_from_slice(s.as_bytes());
+                        f(&mut buf);
+                        hint::black_box(&*buf);
+                    }
+                },
+            ));
+        }
+    }
+    let padded = &padded;
+    for &(name, f) in TRIM_IMPLS.iter().chain(TRIM_START_IMPLS) {
+        benchmarks.push(Benchmark::new(&format!("padded {}", name), move || {
+            for s in padded {
+                hint::black_box(f(s));
+            }
+        }));
+    }
+    rust_str_bench::benchmark(1_000, &benchmarks);
+}
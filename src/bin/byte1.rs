@@ -1,8 +1,17 @@
 #![feature(bench_black_box)]
 
+use std::env;
 use std::hint;
+use std::iter;
 
 use rand::Rng;
+use rust_str_bench::aligned_writer::AlignedWriter;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::adversarial_strings;
+use rust_str_bench::random_strings::random_script_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::random_strings::Script;
+use rust_str_bench::random_strings::SearchInputSpec;
 use rust_str_bench::Benchmark;
 
 fn gen_random_strings() -> Vec<String> {
@@ -24,8 +33,8 @@ fn gen_string() -> String {
 }
 
 #[inline(never)]
-fn find_char_chars_enumerate(s: &str, c: char) -> Option<usize> {
-    for (i, ch) in s.chars().enumerate() {
+fn find_char_char_indices(s: &str, c: char) -> Option<usize> {
+    for (i, ch) in s.char_indices() {
         if ch == c {
             return Some(i);
         }
@@ -40,7 +49,7 @@ fn find_char_string_find_char(s: &str, c: char) -> Option<usize> {
 
 #[inline(never)]
 fn find_char_with_ascii(s: &str, needle: char) -> Option<usize> {
-    if needle as u32 <= 0xf7 {
+    if needle.is_ascii() {
         return s.as_bytes().iter().position(|&b| b == needle as u8);
     }
     find_char_string_find_char(s, needle)
@@ -48,47 +57,330 @@ fn find_char_with_ascii(s: &str, needle: char) -> Option<usize> {
 
 #[inline(never)]
 fn find_char_with_ascii_memchr(s: &str, needle: char) -> Option<usize> {
-    if needle as u32 <= 0xf7 {
+    if needle.is_ascii() {
         return memchr::memchr(needle as u8, s.as_bytes());
     }
     find_char_string_find_char(s, needle)
 }
 
+/// Search for the last byte of needle UTF-8 encoding with `memchr`,
+/// then compare preceding bytes.
+#[inline(never)]
+fn find_char_memchr_last_byte(s: &str, needle: char) -> Option<usize> {
+    let mut buf = [0; 4];
+    let needle = needle.encode_utf8(&mut buf).as_bytes();
+    let last = needle[needle.len() - 1];
+    let haystack = s.as_bytes();
+    if haystack.len() < needle.len() {
+        return None;
+    }
+    let mut start = needle.len() - 1;
+    while let Some(i) = memchr::memchr(last, &haystack[start..]) {
+        let end = start + i + 1;
+        if haystack[..end].ends_with(needle) {
+            return Some(end - needle.len());
+        }
+        start = end;
+    }
+    None
+}
+
 #[inline(never)]
 fn find_str(s: &str, needle: &str) -> Option<usize> {
     s.find(needle)
 }
 
+#[inline(never)]
+fn rfind_char_string_rfind_char(s: &str, c: char) -> Option<usize> {
+    s.rfind(c)
+}
+
+#[inline(never)]
+fn rfind_char_char_indices_rev(s: &str, c: char) -> Option<usize> {
+    for (i, ch) in s.char_indices().rev() {
+        if ch == c {
+            return Some(i);
+        }
+    }
+    None
+}
+
+#[inline(never)]
+fn rfind_char_with_ascii_memrchr(s: &str, needle: char) -> Option<usize> {
+    if needle.is_ascii() {
+        return memchr::memrchr(needle as u8, s.as_bytes());
+    }
+    rfind_char_string_rfind_char(s, needle)
+}
+
+/// Search backwards for the last byte of needle UTF-8 encoding with `memrchr`,
+/// then compare preceding bytes.
+#[inline(never)]
+fn rfind_char_memrchr_last_byte(s: &str, needle: char) -> Option<usize> {
+    let mut buf = [0; 4];
+    let needle = needle.encode_utf8(&mut buf).as_bytes();
+    let last = needle[needle.len() - 1];
+    let haystack = s.as_bytes();
+    let mut end = haystack.len();
+    while let Some(i) = memchr::memrchr(last, &haystack[..end]) {
+        if haystack[..=i].ends_with(needle) {
+            return Some(i + 1 - needle.len());
+        }
+        end = i;
+    }
+    None
+}
+
+#[inline(never)]
+fn rfind_str(s: &str, needle: &str) -> Option<usize> {
+    s.rfind(needle)
+}
+
+#[inline(never)]
+fn rfind_str_memmem(s: &str, needle: &str) -> Option<usize> {
+    memchr::memmem::rfind(s.as_bytes(), needle.as_bytes())
+}
+
+#[inline(never)]
+fn rfind_str_with_ascii_memrchr(s: &str, needle: &str) -> Option<usize> {
+    if let [b] = needle.as_bytes() {
+        return memchr::memrchr(*b, s.as_bytes());
+    }
+    rfind_str(s, needle)
+}
+
+type FindChar = fn(&str, char) -> Option<usize>;
+type FindStr = fn(&str, &str) -> Option<usize>;
+
+const FIND_STR_IMPLS: &[(&str, FindStr)] = &[("str::find(str)", find_str)];
+
+/// Implementations are tested against `str::find` and `str::rfind`, and benchmarked.
+const FIND_CHAR_IMPLS: &[(&str, FindChar)] = &[
+    ("str::find(char)", find_char_string_find_char),
+    ("char_indices", find_char_char_indices),
+    ("find_char_with_ascii", find_char_with_ascii),
+    ("find_char_with_ascii_memchr", find_char_with_ascii_memchr),
+    ("memchr_last_byte", find_char_memchr_last_byte),
+];
+
+const RFIND_STR_IMPLS: &[(&str, FindStr)] = &[
+    ("str::rfind(str)", rfind_str),
+    ("memmem::rfind", rfind_str_memmem),
+    ("rfind_str_with_ascii_memrchr", rfind_str_with_ascii_memrchr),
+];
+
+const RFIND_CHAR_IMPLS: &[(&str, FindChar)] = &[
+    ("str::rfind(char)", rfind_char_string_rfind_char),
+    ("char_indices_rev", rfind_char_char_indices_rev),
+    (
+        "rfind_char_with_ascii_memrchr",
+        rfind_char_with_ascii_memrchr,
+    ),
+    ("memrchr_last_byte", rfind_char_memrchr_last_byte),
+];
+
+struct Workload {
+    name: String,
+    needle: char,
+    strings: Vec<String>,
+}
+
+/// Strings of up to 30 chars from `scripts`, half of them contain `needle`.
+fn gen_script_strings(scripts: &[Script], needle: char) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..1_000)
+        .map(|_| {
+            let len = rng.gen_range(0..=30);
+            let mut chars: Vec<char> = random_script_string(scripts, len).chars().collect();
+            if len != 0 && rng.gen() {
+                chars[rng.gen_range(0..len)] = needle;
+            }
+            chars.into_iter().collect()
+        })
+        .collect()
+}
+
+fn gen_workloads() -> Vec<Workload> {
+    let mut workloads = vec![
+        Workload {
+            name: "ascii 'a'".to_owned(),
+            needle: 'a',
+            strings: gen_random_strings(),
+        },
+        Workload {
+            name: "cyrillic 'ж'".to_owned(),
+            needle: 'ж',
+            strings: gen_script_strings(&[Script::Cyrillic], 'ж'),
+        },
+        Workload {
+            name: "cjk '中'".to_owned(),
+            needle: '中',
+            strings: gen_script_strings(&[Script::Cjk], '中'),
+        },
+        Workload {
+            name: "emoji '😀'".to_owned(),
+            needle: '😀',
+            strings: gen_script_strings(&[Script::Emoji], '😀'),
+        },
+        Workload {
+            name: "mixed 'ж'".to_owned(),
+            needle: 'ж',
+            strings: gen_script_strings(&Script::ALL, 'ж'),
+        },
+        Workload {
+            name: "mixed '中'".to_owned(),
+            needle: '中',
+            strings: gen_script_strings(&Script::ALL, '中'),
+        },
+    ];
+    for spec in SearchInputSpec::cells(0..=30) {
+        workloads.push(Workload {
+            name: format!("ascii 'a' {}", spec.name()),
+            needle: 'a',
+            strings: spec.gen_strings(1_000, 'a', || Script::Ascii.random_char()),
+        });
+    }
+    workloads
+}
+
+type Input = (String, char);
+
+fn test_impls(
+    inputs: &[Input],
+    reference: fn(&str, char) -> Option<usize>,
+    char_impls: &[(&str, FindChar)],
+    str_impls: &[(&str, FindStr)],
+) {
+    let reference = |(s, c): &Input| reference(s, *c);
+    differential_test(inputs, reference, char_impls, |f, (s, c)| f(s, *c));
+    differential_test(inputs, reference, str_impls, |f, (s, c)| {
+        f(s, c.encode_utf8(&mut [0; 4]))
+    });
+}
+
+fn test() {
+    let needles = ['a', 'z', '\u{80}', 'é', '÷', 'ж', '中', '😀'];
+    let mut inputs = Vec::new();
+    for needle in needles {
+        for s in adversarial_strings(needle, 1000) {
+            inputs.push((s, needle));
+        }
+        for _ in 0..1000 {
+            let len = rand::thread_rng().gen_range(0..=20);
+            inputs.push((random_utf8_string(len), needle));
+        }
+    }
+    for w in gen_workloads() {
+        for s in w.strings {
+            inputs.push((s, w.needle));
+        }
+    }
+
+    test_impls(&inputs, |s, c| s.find(c), FIND_CHAR_IMPLS, FIND_STR_IMPLS);
+    test_impls(
+        &inputs,
+        |s, c| s.rfind(c),
+        RFIND_CHAR_IMPLS,
+        RFIND_STR_IMPLS,
+    );
+}
+
+/// Haystack sizes from 16 bytes to 16 MiB, crossing L1, L2 and L3 sizes.
+const SWEEP_SIZES: &[usize] = &[
+    16,
+    64,
+    256,
+    1 << 10,
+    4 << 10,
+    16 << 10,
+    64 << 10,
+    256 << 10,
+    1 << 20,
+    4 << 20,
+    16 << 20,
+];
+
+/// Search for an absent ASCII needle in a single haystack of each size,
+/// so every implementation scans the whole buffer.
+fn sweep() {
+    let mut rng = rand::thread_rng();
+    let mut results = vec![Vec::new(); FIND_CHAR_IMPLS.len()];
+    for &size in SWEEP_SIZES {
+        let haystack: String = (0..size)
+            .map(|_| rng.gen_range(b'b'..=b'z') as char)
+            .collect();
+        println!("size={}", size);
+        let haystack = &haystack;
+        let benchmarks: Vec<_> = FIND_CHAR_IMPLS
+            .iter()
+            .map(|&(name, f)| Benchmark::new(name, move || f(haystack, 'a')))
+            .collect();
+        let seconds = rust_str_bench::benchmark(1, &benchmarks);
+        for (r, s) in results.iter_mut().zip(seconds) {
+            r.push(size as f64 / s / 1e9);
+        }
+    }
+    let mut w = AlignedWriter::new(FIND_CHAR_IMPLS.len() + 1);
+    w.write_n_l(iter::once("size").chain(FIND_CHAR_IMPLS.iter().map(|&(name, _)| name)));
+    for (i, size) in SWEEP_SIZES.iter().enumerate() {
+        w.write(" ");
+        w.write_n_r(
+            iter::once(size.to_string()).chain(results.iter().map(|r| format!("{:.2}", r[i]))),
+        );
+    }
+    println!("GB/s by haystack size:");
+    w.print();
+}
+
+/// Command line options.
+#[derive(Default)]
+struct Args {
+    /// Run the haystack size sweep instead of workloads.
+    sweep: bool,
+}
+
+impl Args {
+    /// Parse `--sweep`.
+    fn parse() -> Args {
+        let mut r = Args::default();
+        for arg in env::args().skip(1) {
+            match arg.as_str() {
+                "--sweep" => r.sweep = true,
+                _ => panic!("unknown argument: {}", arg),
+            }
+        }
+        r
+    }
+}
+
 fn main() {
-    let strings = gen_random_strings();
-    rust_str_bench::benchmark(
-        strings.len(),
-        &[
-            Benchmark::new("str::find(str)", || {
-                for s in &strings {
-                    hint::black_box(find_str(s, "a"));
-                }
-            }),
-            Benchmark::new("str::find(char)", || {
-                for s in &strings {
-                    hint::black_box(find_char_string_find_char(s, 'a'));
-                }
-            }),
-            Benchmark::new("chars_enumerate", || {
-                for s in &strings {
-                    hint::black_box(find_char_chars_enumerate(s, 'a'));
-                }
-            }),
-            Benchmark::new("find_char_with_ascii", || {
-                for s in &strings {
-                    hint::black_box(find_char_with_ascii(s, 'a'));
+    let args = Args::parse();
+    test();
+
+    if args.sweep {
+        sweep();
+        return;
+    }
+
+    let workloads = gen_workloads();
+    let mut benchmarks = Vec::new();
+    for w in &workloads {
+        let needle_str = w.needle.to_string();
+        for &(name, f) in FIND_STR_IMPLS.iter().chain(RFIND_STR_IMPLS) {
+            let needle_str = needle_str.clone();
+            benchmarks.push(Benchmark::new(&format!("{} {}", w.name, name), move || {
+                for s in &w.strings {
+                    hint::black_box(f(s, &needle_str));
                 }
-            }),
-            Benchmark::new("find_char_with_ascii_memchr", || {
-                for s in &strings {
-                    hint::black_box(find_char_with_ascii_memchr(s, 'a'));
+            }));
+        }
+        for &(name, f) in FIND_CHAR_IMPLS.iter().chain(RFIND_CHAR_IMPLS) {
+            benchmarks.push(Benchmark::new(&format!("{} {}", w.name, name), move || {
+                for s in &w.strings {
+                    hint::black_box(f(s, w.needle));
                 }
-            }),
-        ],
-    );
+            }));
+        }
+    }
+    rust_str_bench::benchmark(1_000, &benchmarks);
 }
@@ -0,0 +1,162 @@
+#![feature(portable_simd)]
+
+use std::hint;
+use std::simd::u8x32;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::Benchmark;
+
+#[inline(never)]
+fn count_chars_filter(s: &str, needle: u8) -> usize {
+    s.chars().filter(|&c| c == needle as char).count()
+}
+
+#[inline(never)]
+fn count_bytes_filter(s: &str, needle: u8) -> usize {
+    s.bytes().filter(|&b| b == needle).count()
+}
+
+#[inline(never)]
+fn count_memchr_iter(s: &str, needle: u8) -> usize {
+    memchr::memchr_iter(needle, s.as_bytes()).count()
+}
+
+/// Sum popcounts of 32-byte compare masks.
+#[inline(never)]
+fn count_simd_popcount(s: &str, needle: u8) -> usize {
+    type V = u8x32;
+    let haystack = s.as_bytes();
+    let n = V::splat(needle);
+    let mut count = 0;
+    let mut chunks = haystack.chunks_exact(V::LANES);
+    for chunk in &mut chunks {
+        count += V::from_slice(chunk).simd_eq(n).to_bitmask().count_ones() as usize;
+    }
+    count + chunks.remainder().iter().filter(|&&b| b == needle).count()
+}
+
+#[inline(never)]
+fn positions_char_indices(s: &str, needle: u8) -> Vec<usize> {
+    s.char_indices()
+        .filter(|&(_, c)| c == needle as char)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[inline(never)]
+fn positions_match_indices(s: &str, needle: u8) -> Vec<usize> {
+    s.match_indices(needle as char).map(|(i, _)| i).collect()
+}
+
+#[inline(never)]
+fn positions_memchr_iter(s: &str, needle: u8) -> Vec<usize> {
+    memchr::memchr_iter(needle, s.as_bytes()).collect()
+}
+
+/// Iterate set bits of 32-byte compare masks.
+#[inline(never)]
+fn positions_simd_bitmask(s: &str, needle: u8) -> Vec<usize> {
+    type V = u8x32;
+    let haystack = s.as_bytes();
+    let n = V::splat(needle);
+    let mut positions = Vec::new();
+    let mut i = 0;
+    while i + V::LANES <= haystack.len() {
+        let mut mask = V::from_slice(&haystack[i..]).simd_eq(n).to_bitmask();
+        while mask != 0 {
+            positions.push(i + mask.trailing_zeros() as usize);
+            mask &= mask - 1;
+        }
+        i += V::LANES;
+    }
+    positions.extend((i..haystack.len()).filter(|&j| haystack[j] == needle));
+    positions
+}
+
+type Count = fn(&str, u8) -> usize;
+type Positions = fn(&str, u8) -> Vec<usize>;
+
+const COUNT_IMPLS: &[(&str, Count)] = &[
+    ("count chars_filter", count_chars_filter),
+    ("count bytes_filter", count_bytes_filter),
+    ("count memchr_iter", count_memchr_iter),
+    ("count simd_popcount", count_simd_popcount),
+];
+
+const POSITIONS_IMPLS: &[(&str, Positions)] = &[
+    ("positions char_indices", positions_char_indices),
+    ("positions match_indices", positions_match_indices),
+    ("positions memchr_iter", positions_memchr_iter),
+    ("positions simd_bitmask", positions_simd_bitmask),
+];
+
+/// `count` lowercase strings of length `len`.
+fn gen_strings(count: usize, mut len: impl FnMut() -> usize) -> Vec<String> {
+    (0..count).map(|_| random_string(len())).collect()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs: Vec<(String, u8)> = Vec::new();
+    for _ in 0..10_000 {
+        let len = rng.gen_range(0..=100);
+        let s = if rng.gen() {
+            random_string(len)
+        } else {
+            random_utf8_string(len)
+        };
+        inputs.push((s, rng.gen_range(b'a'..=b'c')));
+    }
+    for s in gen_strings(10, || 5000) {
+        inputs.push((s, b'a'));
+    }
+
+    differential_test(
+        &inputs,
+        |(s, n)| s.matches(*n as char).count(),
+        COUNT_IMPLS,
+        |f, (s, n)| f(s, *n),
+    );
+
+    differential_test(
+        &inputs,
+        |(s, n)| (0..s.len()).filter(|&i| s.as_bytes()[i] == *n).collect(),
+        POSITIONS_IMPLS,
+        |f, (s, n)| f(s, *n),
+    );
+}
+
+fn main() {
+    test();
+
+    let mut rng = rand::thread_rng();
+    let corpora = [
+        ("short", gen_strings(1_000, || rng.gen_range(0..=30))),
+        ("4KiB", gen_strings(10, || 4 << 10)),
+        ("64KiB", gen_strings(10, || 64 << 10)),
+    ];
+    for (corpus, strings) in &corpora {
+        println!("{}", corpus);
+        let mut benchmarks = Vec::new();
+        for &(name, f) in COUNT_IMPLS {
+            benchmarks.push(Benchmark::new(name, move || {
+                for s in strings {
+                    hint::black_box(f(s, b'a'));
+                }
+            }));
+        }
+        for &(name, f) in POSITIONS_IMPLS {
+            benchmarks.push(Benchmark::new(name, move || {
+                for s in strings {
+                    hint::black_box(f(s, b'a'));
+                }
+            }));
+        }
+        rust_str_bench::benchmark(strings.len(), &benchmarks);
+    }
+}
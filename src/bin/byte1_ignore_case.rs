@@ -0,0 +1,233 @@
+#![feature(portable_simd)]
+
+use std::hint;
+use std::simd::u8x16;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::mixed_strings;
+use rust_str_bench::random_strings::random_ascii_string;
+use rust_str_bench::random_strings::random_mixed_case_string;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+#[inline(never)]
+fn find_char_to_lowercase(s: &str, needle: u8) -> Option<usize> {
+    s.to_ascii_lowercase()
+        .find(needle.to_ascii_lowercase() as char)
+}
+
+#[inline(never)]
+fn find_char_eq_ignore_ascii_case(s: &str, needle: u8) -> Option<usize> {
+    s.bytes().position(|b| b.eq_ignore_ascii_case(&needle))
+}
+
+#[inline(never)]
+fn find_char_memchr2(s: &str, needle: u8) -> Option<usize> {
+    memchr::memchr2(
+        needle.to_ascii_lowercase(),
+        needle.to_ascii_uppercase(),
+        s.as_bytes(),
+    )
+}
+
+/// Value to OR haystack bytes with and value to compare to,
+/// so both cases of a letter compare equal.
+fn or_0x20_pattern(b: u8) -> (u8, u8) {
+    if b.is_ascii_alphabetic() {
+        (0x20, b | 0x20)
+    } else {
+        (0, b)
+    }
+}
+
+#[inline(never)]
+fn find_char_simd_or_0x20(s: &str, needle: u8) -> Option<usize> {
+    type V = u8x16;
+    let (or, cmp) = or_0x20_pattern(needle);
+    let (or, cmp) = (V::splat(or), V::splat(cmp));
+    let haystack = s.as_bytes();
+    let mut i = 0;
+    while i + V::LANES <= haystack.len() {
+        let chunk = V::from_slice(&haystack[i..]);
+        let eq = (chunk | or).simd_eq(cmp);
+        if eq.any() {
+            return Some(i + eq.to_bitmask().trailing_zeros() as usize);
+        }
+        i += V::LANES;
+    }
+    haystack[i..]
+        .iter()
+        .position(|b| b.eq_ignore_ascii_case(&needle))
+        .map(|pos| pos + i)
+}
+
+#[inline(never)]
+fn find_str_to_lowercase(s: &str, needle: &str) -> Option<usize> {
+    s.to_ascii_lowercase().find(&needle.to_ascii_lowercase())
+}
+
+#[inline(never)]
+fn find_str_eq_ignore_ascii_case(s: &str, needle: &str) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    s.as_bytes()
+        .windows(needle.len())
+        .position(|w| w.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+/// Find candidates by both cases of the first byte, then verify.
+#[inline(never)]
+fn find_str_memchr2(s: &str, needle: &str) -> Option<usize> {
+    let (haystack, needle) = (s.as_bytes(), needle.as_bytes());
+    let first = match needle.first() {
+        Some(&first) => first,
+        None => return Some(0),
+    };
+    let (lower, upper) = (first.to_ascii_lowercase(), first.to_ascii_uppercase());
+    memchr::memchr2_iter(lower, upper, haystack).find(
+        |&i| matches!(haystack.get(i..i + needle.len()), Some(w) if w.eq_ignore_ascii_case(needle)),
+    )
+}
+
+/// Compare 16 positions at once by needle first and last bytes in both cases,
+/// then verify candidates.
+#[inline(never)]
+fn find_str_simd_or_0x20(s: &str, needle: &str) -> Option<usize> {
+    type V = u8x16;
+    let (haystack, needle) = (s.as_bytes(), needle.as_bytes());
+    let n = needle.len();
+    if n == 0 {
+        return Some(0);
+    }
+    let (first_or, first_cmp) = or_0x20_pattern(needle[0]);
+    let (last_or, last_cmp) = or_0x20_pattern(needle[n - 1]);
+    let (first_or, first_cmp) = (V::splat(first_or), V::splat(first_cmp));
+    let (last_or, last_cmp) = (V::splat(last_or), V::splat(last_cmp));
+    let mut i = 0;
+    while i + n - 1 + V::LANES <= haystack.len() {
+        let a = V::from_slice(&haystack[i..]);
+        let b = V::from_slice(&haystack[i + n - 1..]);
+        let eq = (a | first_or).simd_eq(first_cmp) & (b | last_or).simd_eq(last_cmp);
+        let mut mask = eq.to_bitmask();
+        while mask != 0 {
+            let j = i + mask.trailing_zeros() as usize;
+            if haystack[j..j + n].eq_ignore_ascii_case(needle) {
+                return Some(j);
+            }
+            mask &= mask - 1;
+        }
+        i += V::LANES;
+    }
+    haystack[i..]
+        .windows(n)
+        .position(|w| w.eq_ignore_ascii_case(needle))
+        .map(|pos| pos + i)
+}
+
+type FindChar = fn(&str, u8) -> Option<usize>;
+type FindStr = fn(&str, &str) -> Option<usize>;
+
+const FIND_CHAR_IMPLS: &[(&str, FindChar)] = &[
+    ("char to_lowercase", find_char_to_lowercase),
+    ("char eq_ignore_ascii_case", find_char_eq_ignore_ascii_case),
+    ("char memchr2", find_char_memchr2),
+    ("char simd_or_0x20", find_char_simd_or_0x20),
+];
+
+const FIND_STR_IMPLS: &[(&str, FindStr)] = &[
+    ("str to_lowercase", find_str_to_lowercase),
+    ("str eq_ignore_ascii_case", find_str_eq_ignore_ascii_case),
+    ("str memchr2", find_str_memchr2),
+    ("str simd_or_0x20", find_str_simd_or_0x20),
+];
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs = Vec::new();
+    // All-ASCII haystacks check that OR 0x20 does not match non-letters.
+    for haystack in mixed_strings(10_000, 40, random_mixed_case_string) {
+        let needle = if haystack.is_ascii() && !haystack.is_empty() && rng.gen() {
+            let start = rng.gen_range(0..haystack.len());
+            let end = rng.gen_range(start..=haystack.len().min(start + 5));
+            let mut needle = haystack[start..end].to_owned();
+            needle.make_ascii_uppercase();
+            needle
+        } else {
+            random_ascii_string(rng.gen_range(0..=3))
+        };
+        inputs.push((haystack, needle));
+    }
+
+    // Lowercase both sides, then compare at each position.
+    let reference = |(s, n): &(String, String)| {
+        let (s, n) = (s.to_ascii_lowercase(), n.to_ascii_lowercase());
+        (0..=s.len().checked_sub(n.len())?).find(|&i| s.as_bytes()[i..].starts_with(n.as_bytes()))
+    };
+    differential_test(&inputs, reference, FIND_STR_IMPLS, |f, (s, n)| f(s, n));
+
+    let char_inputs: Vec<(String, u8)> = inputs
+        .into_iter()
+        .filter_map(|(s, n)| Some((s, *n.as_bytes().first()?)))
+        .collect();
+    differential_test(
+        &char_inputs,
+        |(s, n)| s.bytes().position(|b| b.eq_ignore_ascii_case(n)),
+        FIND_CHAR_IMPLS,
+        |f, (s, n)| f(s, *n),
+    );
+}
+
+fn main() {
+    test();
+
+    let mut rng = rand::thread_rng();
+    let corpora: Vec<(&str, Vec<String>)> = vec![
+        (
+            "lower",
+            (0..1_000)
+                .map(|_| random_string(rng.gen_range(0..=30)))
+                .collect(),
+        ),
+        (
+            "mixed",
+            (0..1_000)
+                .map(|_| random_mixed_case_string(rng.gen_range(0..=30)))
+                .collect(),
+        ),
+        (
+            "mixed_long",
+            (0..1_000).map(|_| random_mixed_case_string(256)).collect(),
+        ),
+    ];
+
+    let mut benchmarks = Vec::new();
+    for (corpus, strings) in &corpora {
+        for &(name, f) in FIND_CHAR_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("{} {} 'A'", corpus, name),
+                move || {
+                    for s in strings {
+                        hint::black_box(f(s, b'A'));
+                    }
+                },
+            ));
+        }
+        for needle in ["Ab", "AbcD"] {
+            for &(name, f) in FIND_STR_IMPLS {
+                benchmarks.push(Benchmark::new(
+                    &format!("{} {} {:?}", corpus, name, needle),
+                    move || {
+                        for s in strings {
+                            hint::black_box(f(s, needle));
+                        }
+                    },
+                ));
+            }
+        }
+    }
+    rust_str_bench::benchmark(1_000, &benchmarks);
+}
@@ -0,0 +1,219 @@
+#![feature(portable_simd)]
+
+use std::hint;
+use std::simd::u8x16;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_ascii_string;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::Benchmark;
+
+/// Sets of size `n` are the first `n` bytes, rare letters first
+/// so smaller sets are found later in random lowercase strings.
+const SET_BYTES: &[u8; 16] = b"zqxjkvbpygfwmucl";
+
+const SET_SIZES: &[usize] = &[2, 3, 4, 8, 16];
+
+struct ByteSet {
+    bytes: Vec<u8>,
+    chars: Vec<char>,
+    table: [bool; 256],
+    /// Bit `h` is set in `nibble_lo[l]` if byte `h << 4 | l` is in the set.
+    nibble_lo: u8x16,
+    /// `1 << h` for ASCII high nibbles, zero for non-ASCII.
+    nibble_hi: u8x16,
+}
+
+impl ByteSet {
+    fn new(bytes: &[u8]) -> ByteSet {
+        let mut table = [false; 256];
+        let mut nibble_lo = [0; 16];
+        let mut nibble_hi = [0; 16];
+        for &b in bytes {
+            assert!(b.is_ascii());
+            table[b as usize] = true;
+            nibble_lo[(b & 0xf) as usize] |= 1 << (b >> 4);
+        }
+        for (h, bit) in nibble_hi.iter_mut().enumerate().take(8) {
+            *bit = 1 << h;
+        }
+        ByteSet {
+            bytes: bytes.to_vec(),
+            chars: bytes.iter().map(|&b| b as char).collect(),
+            table,
+            nibble_lo: u8x16::from_array(nibble_lo),
+            nibble_hi: u8x16::from_array(nibble_hi),
+        }
+    }
+}
+
+macro_rules! chars_position_matches {
+    ($name:ident, $($c:literal)|+) => {
+        #[inline(never)]
+        fn $name(s: &str) -> Option<usize> {
+            s.chars().position(|c| matches!(c, $($c)|+))
+        }
+    };
+}
+
+chars_position_matches!(chars_position_matches_2, 'z' | 'q');
+chars_position_matches!(chars_position_matches_3, 'z' | 'q' | 'x');
+chars_position_matches!(chars_position_matches_4, 'z' | 'q' | 'x' | 'j');
+chars_position_matches!(
+    chars_position_matches_8,
+    'z' | 'q' | 'x' | 'j' | 'k' | 'v' | 'b' | 'p'
+);
+chars_position_matches!(
+    chars_position_matches_16,
+    'z' | 'q' | 'x' | 'j' | 'k' | 'v' | 'b' | 'p' | 'y' | 'g' | 'f' | 'w' | 'm' | 'u' | 'c' | 'l'
+);
+
+/// Returns char index, which is equal to byte offset only for ASCII strings.
+fn chars_position_matches(s: &str, set: &ByteSet) -> Option<usize> {
+    match set.bytes.len() {
+        2 => chars_position_matches_2(s),
+        3 => chars_position_matches_3(s),
+        4 => chars_position_matches_4(s),
+        8 => chars_position_matches_8(s),
+        16 => chars_position_matches_16(s),
+        n => panic!("no matches! pattern for set size {}", n),
+    }
+}
+
+#[inline(never)]
+fn str_find_char_slice(s: &str, set: &ByteSet) -> Option<usize> {
+    s.find(set.chars.as_slice())
+}
+
+#[inline(never)]
+fn memchr2(s: &str, set: &ByteSet) -> Option<usize> {
+    memchr::memchr2(set.bytes[0], set.bytes[1], s.as_bytes())
+}
+
+#[inline(never)]
+fn memchr3(s: &str, set: &ByteSet) -> Option<usize> {
+    memchr::memchr3(set.bytes[0], set.bytes[1], set.bytes[2], s.as_bytes())
+}
+
+#[inline(never)]
+fn lookup_table(s: &str, set: &ByteSet) -> Option<usize> {
+    s.bytes().position(|b| set.table[b as usize])
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+#[inline]
+unsafe fn shuffle(table: u8x16, indices: u8x16) -> u8x16 {
+    std::arch::x86_64::_mm_shuffle_epi8(table.into(), indices.into()).into()
+}
+
+/// Classify 16 bytes at a time: byte is in the set if lookups by its low
+/// and high nibble have a common bit.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn nibble_shuffle_ssse3(s: &str, set: &ByteSet) -> Option<usize> {
+    type V = u8x16;
+    let haystack = s.as_bytes();
+    let mut i = 0;
+    while i + V::LANES <= haystack.len() {
+        let chunk = V::from_slice(&haystack[i..]);
+        let lo = shuffle(set.nibble_lo, chunk & V::splat(0xf));
+        let hi = shuffle(set.nibble_hi, chunk >> V::splat(4));
+        let found = (lo & hi).simd_ne(V::splat(0));
+        if found.any() {
+            return Some(i + found.to_bitmask().trailing_zeros() as usize);
+        }
+        i += V::LANES;
+    }
+    haystack[i..]
+        .iter()
+        .position(|&b| set.table[b as usize])
+        .map(|pos| pos + i)
+}
+
+#[inline(never)]
+fn nibble_shuffle(s: &str, set: &ByteSet) -> Option<usize> {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("ssse3") {
+        return unsafe { nibble_shuffle_ssse3(s, set) };
+    }
+    lookup_table(s, set)
+}
+
+type FindByteSet = fn(&str, &ByteSet) -> Option<usize>;
+
+/// Name, applicable set sizes, implementation.
+const IMPLS: &[(&str, &[usize], FindByteSet)] = &[
+    ("chars_position_matches", SET_SIZES, chars_position_matches),
+    ("str::find(&[char])", SET_SIZES, str_find_char_slice),
+    ("memchr2", &[2], memchr2),
+    ("memchr3", &[3], memchr3),
+    ("lookup_table", SET_SIZES, lookup_table),
+    ("nibble_shuffle", SET_SIZES, nibble_shuffle),
+];
+
+fn gen_random_strings() -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..1_000)
+        .map(|_| random_string(rng.gen_range(0..=30)))
+        .collect()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut ascii_inputs = gen_random_strings();
+    ascii_inputs.extend((0..10_000).map(|_| random_ascii_string(rng.gen_range(0..=40))));
+    let utf8_inputs: Vec<String> = (0..10_000)
+        .map(|_| random_utf8_string(rng.gen_range(0..=40)))
+        .collect();
+
+    for &size in SET_SIZES {
+        let set = &ByteSet::new(&SET_BYTES[..size]);
+        let impls: Vec<_> = IMPLS
+            .iter()
+            .filter(|(_, sizes, _)| sizes.contains(&size))
+            .map(|&(name, _, f)| (name, f))
+            .collect();
+        let call = |f: &FindByteSet, s: &String| f(s, set);
+        let reference = |s: &String| s.bytes().position(|b| set.bytes.contains(&b));
+        differential_test(&ascii_inputs, reference, &impls, call);
+        // Char index is not byte offset for non-ASCII strings.
+        let byte_offset_impls: Vec<_> = impls
+            .into_iter()
+            .filter(|(name, _)| *name != "chars_position_matches")
+            .collect();
+        differential_test(&utf8_inputs, reference, &byte_offset_impls, call);
+    }
+}
+
+fn main() {
+    test();
+
+    let strings = gen_random_strings();
+    let sets: Vec<ByteSet> = SET_SIZES
+        .iter()
+        .map(|&size| ByteSet::new(&SET_BYTES[..size]))
+        .collect();
+    let mut benchmarks = Vec::new();
+    for set in &sets {
+        for &(name, sizes, f) in IMPLS {
+            if !sizes.contains(&set.bytes.len()) {
+                continue;
+            }
+            let strings = &strings;
+            benchmarks.push(Benchmark::new(
+                &format!("size={} {}", set.bytes.len(), name),
+                move || {
+                    for s in strings {
+                        hint::black_box(f(s, set));
+                    }
+                },
+            ));
+        }
+    }
+    rust_str_bench::benchmark(strings.len(), &benchmarks);
+}
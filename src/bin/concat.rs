@@ -0,0 +1,184 @@
+use std::fmt::Write;
+use std::hint;
+use std::ops::RangeInclusive;
+
+use rand::Rng;
+use rust_str_bench::aligned_writer::AlignedWriter;
+use rust_str_bench::alloc_counter::count_allocations;
+use rust_str_bench::alloc_counter::CountingAllocator;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const FRAGMENT_COUNTS: &[usize] = &[4, 64, 1024];
+const FRAGMENT_LENS: &[RangeInclusive<usize>] = &[1..=8, 16..=64];
+
+/// Fragments in all lists of one benchmark.
+const TOTAL_FRAGMENTS: usize = 4096;
+
+/// Chunks of at least `CHUNK_SIZE` bytes, appending never moves written bytes.
+#[derive(Default)]
+struct Rope {
+    chunks: Vec<String>,
+}
+
+impl Rope {
+    const CHUNK_SIZE: usize = 4096;
+
+    fn push_str(&mut self, s: &str) {
+        match self.chunks.last_mut() {
+            Some(last) if last.capacity() - last.len() >= s.len() => last.push_str(s),
+            _ => {
+                let mut chunk = String::with_capacity(Rope::CHUNK_SIZE.max(s.len()));
+                chunk.push_str(s);
+                self.chunks.push(chunk);
+            }
+        }
+    }
+
+    fn flatten(&self) -> String {
+        self.chunks.concat()
+    }
+}
+
+#[inline(never)]
+fn push_str(fragments: &[String]) -> String {
+    let mut s = String::new();
+    for f in fragments {
+        s.push_str(f);
+    }
+    s
+}
+
+#[inline(never)]
+fn with_capacity(fragments: &[String]) -> String {
+    let mut s = String::with_capacity(fragments.iter().map(|f| f.len()).sum());
+    for f in fragments {
+        s.push_str(f);
+    }
+    s
+}
+
+/// Common anti-pattern, copies the accumulated string on every step.
+#[inline(never)]
+fn format(fragments: &[String]) -> String {
+    let mut s = String::new();
+    for f in fragments {
+        s = format!("{}{}", s, f);
+    }
+    s
+}
+
+#[inline(never)]
+fn concat(fragments: &[String]) -> String {
+    fragments.concat()
+}
+
+#[inline(never)]
+fn join(fragments: &[String]) -> String {
+    fragments.join("")
+}
+
+#[inline(never)]
+fn write(fragments: &[String]) -> String {
+    let mut s = String::new();
+    for f in fragments {
+        write!(s, "{}", f).unwrap();
+    }
+    s
+}
+
+/// Builds the rope only, flattening is not included.
+#[inline(never)]
+fn rope(fragments: &[String]) -> Rope {
+    let mut rope = Rope::default();
+    for f in fragments {
+        rope.push_str(f);
+    }
+    rope
+}
+
+type Build = fn(&[String]) -> String;
+
+const IMPLS: &[(&str, Build)] = &[
+    ("push_str", push_str),
+    ("with_capacity", with_capacity),
+    ("format!", format),
+    ("concat", concat),
+    ("join", join),
+    ("write!", write),
+];
+
+fn gen_fragments(count: usize, len: &RangeInclusive<usize>) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| random_string(rng.gen_range(len.clone())))
+        .collect()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Vec<String>> = (0..1_000)
+        .map(|_| gen_fragments(rng.gen_range(0..=100), &(0..=5000)))
+        .collect();
+    differential_test(
+        &inputs,
+        |fragments| fragments.concat(),
+        IMPLS,
+        |f, fragments| f(fragments),
+    );
+    differential_test(
+        &inputs,
+        |fragments| fragments.concat(),
+        &[("rope", rope)],
+        |f, fragments| f(fragments).flatten(),
+    );
+}
+
+fn main() {
+    test();
+
+    for &count in FRAGMENT_COUNTS {
+        for len in FRAGMENT_LENS {
+            let lists: Vec<Vec<String>> = (0..TOTAL_FRAGMENTS / count)
+                .map(|_| gen_fragments(count, len))
+                .collect();
+            let lists = &lists;
+            println!("fragments={} len={:?}", count, len);
+
+            let mut benchmarks: Vec<Benchmark> = IMPLS
+                .iter()
+                .map(|&(name, f)| {
+                    Benchmark::new(name, move || {
+                        for fragments in lists {
+                            hint::black_box(f(fragments));
+                        }
+                    })
+                })
+                .collect();
+            benchmarks.push(Benchmark::new("rope", move || {
+                for fragments in lists {
+                    hint::black_box(rope(fragments));
+                }
+            }));
+            rust_str_bench::benchmark(lists.len(), &benchmarks);
+
+            let mut allocs: Vec<(&str, _)> = IMPLS
+                .iter()
+                .map(|&(name, f)| (name, count_allocations(|| f(&lists[0])).1))
+                .collect();
+            allocs.push(("rope", count_allocations(|| rope(&lists[0])).1));
+            let mut w = AlignedWriter::new(allocs.len());
+            w.write_n_l(allocs.iter().map(|(name, _)| format!("{}:", name)));
+            w.write(" allocations=");
+            w.write_n_r(allocs.iter().map(|(_, stats)| stats.allocations));
+            w.write(" bytes=");
+            w.write_n_r(allocs.iter().map(|(_, stats)| stats.bytes));
+            println!("Allocations per build:");
+            w.print();
+        }
+    }
+}
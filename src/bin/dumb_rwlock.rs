@@ -1,21 +1,53 @@
 use std::hint;
+use std::panic;
+use std::panic::AssertUnwindSafe;
 use std::sync::atomic;
 use std::sync::atomic::AtomicUsize;
-use std::sync::Arc;
-use std::thread;
-use std::time::Duration;
-use std::time::Instant;
 
-#[derive(Default)]
-struct DumbRwLock {
-    state: AtomicUsize,
+use rust_str_bench::lock_bench::lock_benchmark;
+use rust_str_bench::lock_bench::LockBenchArgs;
+use rust_str_bench::model_check::model_check;
+use rust_str_bench::model_check::AtomicUsizeOps;
+use rust_str_bench::model_check::ModelAtomicUsize;
+
+/// Generic over atomic to be model checked.
+struct DumbRwLock<A = AtomicUsize> {
+    state: A,
+}
+
+impl<A: AtomicUsizeOps> Default for DumbRwLock<A> {
+    fn default() -> Self {
+        DumbRwLock::with_state(0)
+    }
 }
 
-impl DumbRwLock {
+impl<A: AtomicUsizeOps> DumbRwLock<A> {
+    fn with_state(state: usize) -> Self {
+        DumbRwLock {
+            state: A::new(state),
+        }
+    }
+
     fn lock_shared(&self) {
+        self.lock_shared_fetch_add();
+    }
+
+    fn unlock_shared(&self) {
+        self.unlock_shared_fetch_sub();
+    }
+
+    /// Silently wraps on overflow.
+    fn lock_shared_fetch_add(&self) {
         self.state.fetch_add(1, atomic::Ordering::Acquire);
-        return;
+    }
+
+    /// Silently wraps on underflow.
+    fn unlock_shared_fetch_sub(&self) {
+        self.state.fetch_sub(1, atomic::Ordering::Release);
+    }
 
+    /// Panics on overflow.
+    fn lock_shared_cas(&self) {
         let mut state = self.state.load(atomic::Ordering::Relaxed);
         loop {
             let new_state = state.checked_add(1).unwrap();
@@ -31,10 +63,8 @@ impl DumbRwLock {
         }
     }
 
-    fn unlock_shared(&self) {
-        self.state.fetch_sub(1, atomic::Ordering::Release);
-        return;
-
+    /// Panics on underflow.
+    fn unlock_shared_cas(&self) {
         let mut state = self.state.load(atomic::Ordering::Relaxed);
         loop {
             let new_state = state.checked_sub(1).unwrap();
@@ -51,32 +81,94 @@ impl DumbRwLock {
     }
 }
 
-fn main() {
-    let n_threads = 8;
-    let rwlock = DumbRwLock::default();
-    let rwlock = Arc::new(rwlock);
-
-    let mut threads = Vec::new();
-    for i in 0..n_threads {
-        let rwlock = rwlock.clone();
-        threads.push(thread::spawn(move || loop {
-            let start = Instant::now();
-            let batch = 1000_0000;
-            let mut total = 0;
-            while start.elapsed() < Duration::from_secs(1) {
-                for _ in 0..batch {
-                    rwlock.lock_shared();
-                    hint::black_box(&rwlock);
-                    rwlock.unlock_shared();
-                }
-                total += batch;
+type ModelLock = DumbRwLock<ModelAtomicUsize>;
+
+/// Lock and unlock functions used by one thread.
+type LockImpl = (fn(&ModelLock), fn(&ModelLock));
+
+const FETCH_ADD: LockImpl = (
+    ModelLock::lock_shared_fetch_add,
+    ModelLock::unlock_shared_fetch_sub,
+);
+const CAS: LockImpl = (ModelLock::lock_shared_cas, ModelLock::unlock_shared_cas);
+
+#[derive(Default)]
+struct ModelState {
+    lock: ModelLock,
+    /// Number of threads between lock and unlock.
+    holders: AtomicUsize,
+}
+
+/// Thread `i` takes and releases the lock `rounds` times with `impls[i]`,
+/// lock state must be equal to the number of holders at every step.
+///
+/// The lock has only a shared mode, so there is no mutual exclusion to check:
+/// any number of holders is valid as long as no count is lost.
+fn test_counts(impls: &[LockImpl], rounds: usize) {
+    let executions = model_check(
+        impls.len(),
+        ModelState::default,
+        |s, i| {
+            let (lock, unlock) = impls[i];
+            for _ in 0..rounds {
+                lock(&s.lock);
+                let holders = s.holders.fetch_add(1, atomic::Ordering::SeqCst) + 1;
+                assert_eq!(holders, s.lock.state.peek());
+                unlock(&s.lock);
+                let holders = s.holders.fetch_sub(1, atomic::Ordering::SeqCst) - 1;
+                assert_eq!(holders, s.lock.state.peek());
             }
-            let elapsed_ns = start.elapsed().as_nanos() as u64;
-            println!("Thread {}: {}ns", i, elapsed_ns / total);
-        }));
-    }
+        },
+        |s| assert_eq!(0, s.lock.state.peek()),
+    );
+    println!(
+        "threads={} rounds={}: {} executions ok",
+        impls.len(),
+        rounds,
+        executions
+    );
+}
 
-    for thread in threads {
-        thread.join().unwrap();
-    }
+fn assert_panics(f: impl FnOnce()) {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(|_| {}));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    panic::set_hook(hook);
+    assert!(result.is_err(), "expected panic");
+}
+
+fn test_overflow() {
+    let lock = DumbRwLock::<AtomicUsize>::with_state(usize::MAX);
+    assert_panics(|| lock.lock_shared_cas());
+    assert_eq!(usize::MAX, lock.state.load(atomic::Ordering::Relaxed));
+    lock.lock_shared_fetch_add();
+    assert_eq!(0, lock.state.load(atomic::Ordering::Relaxed));
+
+    let lock = DumbRwLock::<AtomicUsize>::with_state(0);
+    assert_panics(|| lock.unlock_shared_cas());
+    assert_eq!(0, lock.state.load(atomic::Ordering::Relaxed));
+    lock.unlock_shared_fetch_sub();
+    assert_eq!(usize::MAX, lock.state.load(atomic::Ordering::Relaxed));
+}
+
+fn test() {
+    println!("Model checking...");
+    test_counts(&[FETCH_ADD, FETCH_ADD], 2);
+    test_counts(&[FETCH_ADD, FETCH_ADD, FETCH_ADD], 1);
+    // Retry loops make CAS executions numerous, three CAS threads take a minute.
+    test_counts(&[CAS, CAS], 1);
+    test_counts(&[FETCH_ADD, CAS], 2);
+    test_overflow();
+}
+
+fn main() {
+    test();
+
+    let args = LockBenchArgs::parse();
+    let rwlock = DumbRwLock::<AtomicUsize>::default();
+    lock_benchmark(&args, || {
+        rwlock.lock_shared();
+        hint::black_box(&rwlock);
+        rwlock.unlock_shared();
+    });
 }
@@ -0,0 +1,91 @@
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use rust_str_bench::affinity;
+use rust_str_bench::affinity::Placement;
+use rust_str_bench::concurrent::concurrent_benchmark;
+use rust_str_bench::concurrent::ConcurrentBenchmark;
+
+const N_THREADS: usize = 8;
+
+/// One counter per thread.
+trait Counters: Default + Sync {
+    fn counter(&self, thread: usize) -> &AtomicU64;
+}
+
+/// All counters fit in one 64-byte cache line.
+#[derive(Default)]
+#[repr(C, align(64))]
+struct Packed([AtomicU64; N_THREADS]);
+
+impl Counters for Packed {
+    fn counter(&self, thread: usize) -> &AtomicU64 {
+        &self.0[thread]
+    }
+}
+
+#[derive(Default)]
+#[repr(align(64))]
+struct Aligned64(AtomicU64);
+
+#[derive(Default)]
+struct Padded64([Aligned64; N_THREADS]);
+
+impl Counters for Padded64 {
+    fn counter(&self, thread: usize) -> &AtomicU64 {
+        &self.0[thread].0
+    }
+}
+
+/// Adjacent-line prefetcher on Intel pulls cache lines in pairs,
+/// so 64-byte padding may not be enough.
+#[derive(Default)]
+#[repr(align(128))]
+struct Aligned128(AtomicU64);
+
+#[derive(Default)]
+struct Padded128([Aligned128; N_THREADS]);
+
+impl Counters for Padded128 {
+    fn counter(&self, thread: usize) -> &AtomicU64 {
+        &self.0[thread].0
+    }
+}
+
+fn bm_counters<'a, C: Counters>(
+    name: &str,
+    ordering: Ordering,
+    counters: &'a C,
+) -> ConcurrentBenchmark<'a> {
+    ConcurrentBenchmark::new(&format!("{} {:?}", name, ordering), move |thread| {
+        counters.counter(thread).fetch_add(1, ordering)
+    })
+}
+
+fn main() {
+    let packed = Packed::default();
+    let padded_64 = Padded64::default();
+    let padded_128 = Padded128::default();
+
+    let mut benchmarks = Vec::new();
+    for ordering in [Ordering::Relaxed, Ordering::AcqRel, Ordering::SeqCst] {
+        benchmarks.push(bm_counters("packed", ordering, &packed));
+        benchmarks.push(bm_counters("padded_64", ordering, &padded_64));
+        benchmarks.push(bm_counters("padded_128", ordering, &padded_128));
+    }
+    // Compact placement puts counters of SMT siblings in shared L1,
+    // spread placement makes cache lines travel between cores and sockets.
+    // Pinned runs use at most one thread per allowed CPU.
+    for placement in [Placement::Unpinned, Placement::Compact, Placement::Spread] {
+        let n_threads = match placement {
+            Placement::Unpinned => N_THREADS,
+            _ => N_THREADS.min(affinity::cpu_order(placement).len()),
+        };
+        if n_threads < 2 {
+            println!("skipping {:?}: fewer than 2 CPUs allowed", placement);
+            continue;
+        }
+        concurrent_benchmark(n_threads, Duration::from_secs(2), placement, &benchmarks);
+    }
+}
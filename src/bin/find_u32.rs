@@ -11,6 +11,7 @@ use std::simd::SupportedLaneCount;
 use std::simd::ToBitMask;
 
 use rand::Rng;
+use rust_str_bench::random_strings::SearchInputSpec;
 use rust_str_bench::Benchmark;
 
 /// Copy-paste this into the playground.
@@ -26,23 +27,12 @@ pub fn test_256_do_something(haystack: &[u32], needle: u32) -> Option<usize> {
     }
 }
 
-fn gen_inputs() -> Vec<(Vec<u32>, u32)> {
-    let mut rng = rand::thread_rng();
-    let mut inputs = Vec::new();
-    for _ in 0..1000 {
-        let mut haystack = Vec::new();
-        let len = rng.gen_range(0..=12);
-        for i in 0..len {
-            haystack.push(i);
-        }
-        let needle = if len != 0 && rng.gen() {
-            rng.gen_range(0..len)
-        } else {
-            22
-        };
-        inputs.push((haystack, needle));
-    }
-    inputs
+fn gen_inputs(spec: &SearchInputSpec) -> Vec<(Vec<u32>, u32)> {
+    let needle = 22;
+    spec.gen(1000, &needle, || rand::thread_rng().gen_range(0..32))
+        .into_iter()
+        .map(|haystack| (haystack, needle))
+        .collect()
 }
 
 fn find_position(haystack: &[u32], needle: u32) -> Option<usize> {
@@ -103,55 +93,113 @@ pub fn find_position_simd_128(haystack: &[u32], needle: u32) -> Option<usize> {
     find_position(&haystack[i..], needle).map(|pos| pos + i)
 }
 
+fn rfind_position(haystack: &[u32], needle: u32) -> Option<usize> {
+    haystack.iter().rposition(|&b| b == needle)
+}
+
+/// Scan chunks from the end, highest set bit of the mask is the last match in the chunk.
+#[inline]
+fn rfind_position_simd<const LANES: usize>(haystack: &[u32], needle: u32) -> Option<usize>
+where
+    LaneCount<LANES>: SupportedLaneCount,
+    Mask<i32, LANES>: ToBitMask<BitMask = u8>,
+{
+    let needles = Simd::<u32, LANES>::splat(needle);
+    let mut end = haystack.len();
+    while end >= LANES {
+        let chunk = Simd::from_slice(&haystack[end - LANES..end]);
+        let eq = chunk.simd_eq(needles);
+        if eq.any() {
+            let last = u8::BITS - 1 - eq.to_bitmask().leading_zeros();
+            return Some(end - LANES + last as usize);
+        }
+        end -= LANES;
+    }
+    rfind_position(&haystack[..end], needle)
+}
+
+pub fn rfind_position_simd_128_generic(haystack: &[u32], needle: u32) -> Option<usize> {
+    rfind_position_simd::<4>(haystack, needle)
+}
+
+pub fn rfind_position_simd_256_generic(haystack: &[u32], needle: u32) -> Option<usize> {
+    rfind_position_simd::<8>(haystack, needle)
+}
+
 fn test() {
-    let inputs = gen_inputs();
-    for (haystack, needle) in inputs {
-        let pos = find_position(&haystack, needle);
-        assert_eq!(pos, find_position_simd_128_generic(&haystack, needle));
-        assert_eq!(pos, find_position_simd_256_generic(&haystack, needle));
-        assert_eq!(pos, find_position_simd_128(&haystack, needle));
+    let mut specs = SearchInputSpec::cells(0..=12);
+    // Many matches per haystack, so forward search must find the first one
+    // and reverse search the last one.
+    for mut spec in SearchInputSpec::cells(0..=20) {
+        spec.repeated_needles = true;
+        specs.push(spec);
+    }
+    let needle = 22;
+    for spec in &specs {
+        let filler = if spec.repeated_needles { 20..24 } else { 0..32 };
+        let haystacks = spec.gen(1000, &needle, || {
+            rand::thread_rng().gen_range(filler.clone())
+        });
+        for haystack in haystacks {
+            let pos = find_position(&haystack, needle);
+            assert_eq!(pos, find_position_simd_128_generic(&haystack, needle));
+            assert_eq!(pos, find_position_simd_256_generic(&haystack, needle));
+            assert_eq!(pos, find_position_simd_128(&haystack, needle));
+            let pos = rfind_position(&haystack, needle);
+            assert_eq!(pos, rfind_position_simd_128_generic(&haystack, needle));
+            assert_eq!(pos, rfind_position_simd_256_generic(&haystack, needle));
+        }
     }
 }
 
+type FindPosition = fn(&[u32], u32) -> Option<usize>;
+
+const FIND_POSITION_IMPLS: &[(&str, FindPosition)] = &[
+    ("find_position", find_position),
+    (
+        "find_position_simd_128_generic",
+        find_position_simd_128_generic,
+    ),
+    ("find_position_simd_128", find_position_simd_128),
+    (
+        "find_position_simd_256_generic",
+        find_position_simd_256_generic,
+    ),
+    (
+        "find_position_simd_256_with_target_feature",
+        find_position_simd_256_with_target_feature,
+    ),
+];
+
+const RFIND_POSITION_IMPLS: &[(&str, FindPosition)] = &[
+    ("rfind_position", rfind_position),
+    (
+        "rfind_position_simd_128_generic",
+        rfind_position_simd_128_generic,
+    ),
+    (
+        "rfind_position_simd_256_generic",
+        rfind_position_simd_256_generic,
+    ),
+];
+
 fn main() {
     test();
 
-    let inputs = gen_inputs();
-    rust_str_bench::benchmark(
-        inputs.len(),
-        &[
-            Benchmark::new("find_position", || {
-                hint::black_box(&inputs);
-                for (haystack, needle) in &inputs {
-                    hint::black_box(find_position(haystack, *needle));
-                }
-            }),
-            Benchmark::new("find_position_simd_128_generic", || {
-                hint::black_box(&inputs);
-                for (haystack, needle) in &inputs {
-                    hint::black_box(find_position_simd_128_generic(haystack, *needle));
-                }
-            }),
-            Benchmark::new("find_position_simd_128", || {
-                hint::black_box(&inputs);
-                for (haystack, needle) in &inputs {
-                    hint::black_box(find_position_simd_128(haystack, *needle));
-                }
-            }),
-            Benchmark::new("find_position_simd_256_generic", || {
-                hint::black_box(&inputs);
-                for (haystack, needle) in &inputs {
-                    hint::black_box(find_position_simd_256_generic(haystack, *needle));
+    let cells: Vec<_> = SearchInputSpec::cells(0..=12)
+        .into_iter()
+        .map(|spec| (spec.name(), gen_inputs(&spec)))
+        .collect();
+    let mut benchmarks = Vec::new();
+    for (cell, inputs) in &cells {
+        for &(name, f) in FIND_POSITION_IMPLS.iter().chain(RFIND_POSITION_IMPLS) {
+            benchmarks.push(Benchmark::new(&format!("{} {}", cell, name), move || {
+                hint::black_box(inputs);
+                for (haystack, needle) in inputs {
+                    hint::black_box(f(haystack, *needle));
                 }
-            }),
-            Benchmark::new("find_position_simd_256_with_target_feature", || {
-                hint::black_box(&inputs);
-                for (haystack, needle) in &inputs {
-                    hint::black_box(find_position_simd_256_with_target_feature(
-                        haystack, *needle,
-                    ));
-                }
-            }),
-        ],
-    );
+            }));
+        }
+    }
+    rust_str_bench::benchmark(1000, &benchmarks);
 }
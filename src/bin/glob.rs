@@ -0,0 +1,260 @@
+use std::hint;
+
+use memchr::memmem;
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+// `*` matches any sequence of bytes including `/`, `?` matches any single byte.
+
+#[inline(never)]
+fn recursive(pattern: &[u8], s: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => s.is_empty(),
+        Some((b'*', rest)) => (0..=s.len()).any(|i| recursive(rest, &s[i..])),
+        Some((b'?', rest)) => !s.is_empty() && recursive(rest, &s[1..]),
+        Some((&c, rest)) => s.first() == Some(&c) && recursive(rest, &s[1..]),
+    }
+}
+
+/// On mismatch, backtrack only to the last `*` and let it consume one more byte:
+/// earlier stars can never be needed to match more.
+#[inline(never)]
+fn two_pointer(pattern: &[u8], s: &[u8]) -> bool {
+    let (mut p, mut i) = (0, 0);
+    // Position after the last star and the string position it was tried at.
+    let mut star: Option<(usize, usize)> = None;
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                star = Some((p, i));
+            }
+            Some(&c) if c == b'?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                Some((star_p, star_i)) => {
+                    p = star_p;
+                    i = star_i + 1;
+                    star = Some((star_p, star_i + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// Part of a pattern between stars.
+struct Segment {
+    bytes: Vec<u8>,
+    /// Present if the segment has no `?`.
+    finder: Option<memmem::Finder<'static>>,
+}
+
+impl Segment {
+    fn new(bytes: &[u8]) -> Segment {
+        Segment {
+            bytes: bytes.to_vec(),
+            finder: if bytes.contains(&b'?') {
+                None
+            } else {
+                Some(memmem::Finder::new(bytes).into_owned())
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn matches_at(&self, s: &[u8]) -> bool {
+        s.len() >= self.len() && self.bytes.iter().zip(s).all(|(&p, &b)| p == b'?' || p == b)
+    }
+
+    /// Leftmost match position.
+    fn find(&self, s: &[u8]) -> Option<usize> {
+        match &self.finder {
+            Some(finder) => finder.find(s),
+            None => (0..(s.len() + 1).checked_sub(self.len())?).find(|&i| self.matches_at(&s[i..])),
+        }
+    }
+}
+
+/// Pattern split at stars: the first and the last segments are anchored,
+/// middle segments are found left to right, leftmost match is always the best choice.
+struct CompiledGlob {
+    first: Segment,
+    middle: Vec<Segment>,
+    /// `None` if there are no stars.
+    last: Option<Segment>,
+}
+
+impl CompiledGlob {
+    fn new(pattern: &[u8]) -> CompiledGlob {
+        let mut segments: Vec<&[u8]> = pattern.split(|&c| c == b'*').collect();
+        let first = Segment::new(segments.remove(0));
+        let last = segments.pop().map(Segment::new);
+        let middle = segments
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .map(Segment::new)
+            .collect();
+        CompiledGlob {
+            first,
+            middle,
+            last,
+        }
+    }
+
+    #[inline(never)]
+    fn matches(&self, s: &[u8]) -> bool {
+        let last = match &self.last {
+            Some(last) => last,
+            None => return s.len() == self.first.len() && self.first.matches_at(s),
+        };
+        if s.len() < self.first.len() + last.len()
+            || !self.first.matches_at(s)
+            || !last.matches_at(&s[s.len() - last.len()..])
+        {
+            return false;
+        }
+        let mut rest = &s[self.first.len()..s.len() - last.len()];
+        for segment in &self.middle {
+            match segment.find(rest) {
+                Some(i) => rest = &rest[i + segment.len()..],
+                None => return false,
+            }
+        }
+        true
+    }
+}
+
+#[inline(never)]
+fn compiled(pattern: &[u8], s: &[u8]) -> bool {
+    CompiledGlob::new(pattern).matches(s)
+}
+
+type Match = fn(&[u8], &[u8]) -> bool;
+
+const IMPLS: &[(&str, Match)] = &[
+    ("recursive", recursive),
+    ("two_pointer", two_pointer),
+    ("compiled", compiled),
+];
+
+/// Implementations which match the pattern directly, without compiling it first.
+const UNCOMPILED_IMPLS: &[(&str, Match)] =
+    &[("recursive", recursive), ("two_pointer", two_pointer)];
+
+/// `dp[i][j]`: first `i` pattern bytes match first `j` string bytes.
+fn dp_reference(pattern: &[u8], s: &[u8]) -> bool {
+    let mut dp = vec![vec![false; s.len() + 1]; pattern.len() + 1];
+    dp[0][0] = true;
+    for i in 1..=pattern.len() {
+        for j in 0..=s.len() {
+            dp[i][j] = match pattern[i - 1] {
+                b'*' => dp[i - 1][j] || (j > 0 && dp[i][j - 1]),
+                c => j > 0 && dp[i - 1][j - 1] && (c == b'?' || c == s[j - 1]),
+            };
+        }
+    }
+    dp[pattern.len()][s.len()]
+}
+
+const EXTENSIONS: &[&str] = &["rs", "txt", "jpg", "json", "md", "tar.gz"];
+
+/// Paths like `ab/cdefg/hij_12.rs`.
+fn gen_file_name() -> String {
+    let mut rng = rand::thread_rng();
+    let mut name = String::new();
+    for _ in 0..rng.gen_range(0..=3) {
+        name += &random_string(rng.gen_range(2..=8));
+        name.push('/');
+    }
+    name += &random_string(rng.gen_range(3..=12));
+    if rng.gen() {
+        name += &format!("_{}", rng.gen_range(0..10_000));
+    }
+    name.push('.');
+    name += EXTENSIONS[rng.gen_range(0..EXTENSIONS.len())];
+    name
+}
+
+const PATTERNS: &[&str] = &[
+    "*.rs",
+    "*.tar.gz",
+    "ab*",
+    "*test*",
+    "*_????.jpg",
+    "*a*b*c*.json",
+    "??/*/*.md",
+];
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs: Vec<(String, String)> = Vec::new();
+    let random_over = |alphabet: &[u8], max_len: usize| -> String {
+        let mut rng = rand::thread_rng();
+        (0..rng.gen_range(0..=max_len))
+            .map(|_| alphabet[rng.gen_range(0..alphabet.len())] as char)
+            .collect()
+    };
+    for _ in 0..100_000 {
+        inputs.push((random_over(b"ab?*", 8), random_over(b"ab", 12)));
+    }
+    for n in 0..40 {
+        for pattern in ["*a*a*a*b", "*a*a*a*", "a*a*a*a", "*?a*?*a??*"] {
+            inputs.push((pattern.to_owned(), "a".repeat(n)));
+            inputs.push((pattern.to_owned(), "a".repeat(n) + "b"));
+        }
+    }
+    for _ in 0..1_000 {
+        let pattern = PATTERNS[rng.gen_range(0..PATTERNS.len())];
+        inputs.push((pattern.to_owned(), gen_file_name()));
+    }
+    differential_test(
+        &inputs,
+        |(p, s)| dp_reference(p.as_bytes(), s.as_bytes()),
+        IMPLS,
+        |f, (p, s)| f(p.as_bytes(), s.as_bytes()),
+    );
+}
+
+fn bm_pattern<'a>(name: &str, pattern: &'a str, strings: &'a [String]) -> Vec<Benchmark<'a>> {
+    let mut benchmarks: Vec<Benchmark> = UNCOMPILED_IMPLS
+        .iter()
+        .map(|&(impl_name, f)| {
+            Benchmark::new(&format!("{} {}", name, impl_name), move || {
+                for s in strings {
+                    hint::black_box(f(pattern.as_bytes(), s.as_bytes()));
+                }
+            })
+        })
+        .collect();
+    // Tools compile a pattern once and match it against many names.
+    let glob = CompiledGlob::new(pattern.as_bytes());
+    benchmarks.push(Benchmark::new(&format!("{} compiled", name), move || {
+        for s in strings {
+            hint::black_box(glob.matches(s.as_bytes()));
+        }
+    }));
+    benchmarks
+}
+
+fn main() {
+    test();
+
+    let names: Vec<String> = (0..1_000).map(|_| gen_file_name()).collect();
+    // Recursive matching takes O(n^4) for four stars.
+    let pathological: Vec<String> = vec!["a".repeat(64); 1_000];
+    let mut benchmarks = Vec::new();
+    for &pattern in PATTERNS {
+        benchmarks.extend(bm_pattern(&format!("{:<12}", pattern), pattern, &names));
+    }
+    benchmarks.extend(bm_pattern("*a*a*a*b a^64", "*a*a*a*b", &pathological));
+    rust_str_bench::benchmark(1_000, &benchmarks);
+}
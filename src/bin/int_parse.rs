@@ -0,0 +1,238 @@
+use std::fmt::Write;
+use std::hint;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::Benchmark;
+
+const DIGIT_COUNTS: &[u32] = &[1, 2, 4, 8, 12, 16, 20];
+
+#[inline(never)]
+fn parse_std(s: &str) -> Option<u64> {
+    s.parse().ok()
+}
+
+/// Digits after an optional `+`, like `str::parse` accepts.
+#[inline]
+fn digits(s: &str) -> Option<&[u8]> {
+    let digits = s.strip_prefix('+').unwrap_or(s).as_bytes();
+    if digits.is_empty() {
+        return None;
+    }
+    Some(digits)
+}
+
+#[inline(never)]
+fn parse_scalar(s: &str) -> Option<u64> {
+    let mut n: u64 = 0;
+    for &b in digits(s)? {
+        let d = b.wrapping_sub(b'0');
+        if d > 9 {
+            return None;
+        }
+        n = n.checked_mul(10)?.checked_add(d as u64)?;
+    }
+    Some(n)
+}
+
+/// Value of 8 ASCII digits in little-endian `x`, `None` if any byte is not a digit.
+#[inline]
+fn parse_8_digits(x: u64) -> Option<u64> {
+    const ONES: u64 = 0x0101_0101_0101_0101;
+    // High nibble is 3, and low nibble is at most 9 so adding 6 does not carry into it.
+    if (x & (ONES * 0xf0)) != ONES * 0x30
+        || (x.wrapping_add(ONES * 6) & (ONES * 0xf0)) != ONES * 0x30
+    {
+        return None;
+    }
+    let x = x - ONES * 0x30;
+    // Combine adjacent digits into 2-digit values in every other byte.
+    let x = x.wrapping_mul(10).wrapping_add(x >> 8);
+    // Combine 2-digit values into a 4-digit value in each 32-bit half,
+    // and both halves into the high 32 bits.
+    let x = ((x & 0x0000_00ff_0000_00ff).wrapping_mul(100 + (1_000_000 << 32)))
+        .wrapping_add(((x >> 16) & 0x0000_00ff_0000_00ff).wrapping_mul(1 + (10_000 << 32)))
+        >> 32;
+    Some(x)
+}
+
+#[inline(never)]
+fn parse_swar(s: &str) -> Option<u64> {
+    let mut chunks = digits(s)?.chunks_exact(8);
+    let mut n: u64 = 0;
+    for chunk in &mut chunks {
+        let x = parse_8_digits(u64::from_le_bytes(chunk.try_into().unwrap()))?;
+        n = n.checked_mul(100_000_000)?.checked_add(x)?;
+    }
+    for &b in chunks.remainder() {
+        let d = b.wrapping_sub(b'0');
+        if d > 9 {
+            return None;
+        }
+        n = n.checked_mul(10)?.checked_add(d as u64)?;
+    }
+    Some(n)
+}
+
+#[inline(never)]
+fn format_to_string(n: u64, out: &mut String) {
+    out.push_str(&n.to_string());
+}
+
+#[inline(never)]
+fn format_write(n: u64, out: &mut String) {
+    write!(out, "{}", n).unwrap();
+}
+
+/// `"00010203...99"`.
+const DIGIT_PAIRS: [u8; 200] = {
+    let mut pairs = [0; 200];
+    let mut i = 0;
+    while i < 100 {
+        pairs[2 * i] = b'0' + (i / 10) as u8;
+        pairs[2 * i + 1] = b'0' + (i % 10) as u8;
+        i += 1;
+    }
+    pairs
+};
+
+/// Two digits at a time from the end using `DIGIT_PAIRS`.
+#[inline(never)]
+fn format_lut(mut n: u64, out: &mut String) {
+    let mut buf = [0; 20];
+    let mut i = buf.len();
+    while n >= 100 {
+        let pair = (n % 100) as usize * 2;
+        n /= 100;
+        i -= 2;
+        buf[i..i + 2].copy_from_slice(&DIGIT_PAIRS[pair..pair + 2]);
+    }
+    if n >= 10 {
+        i -= 2;
+        buf[i..i + 2].copy_from_slice(&DIGIT_PAIRS[n as usize * 2..n as usize * 2 + 2]);
+    } else {
+        i -= 1;
+        buf[i] = b'0' + n as u8;
+    }
+    out.push_str(std::str::from_utf8(&buf[i..]).unwrap());
+}
+
+type Parse = fn(&str) -> Option<u64>;
+type Format = fn(u64, &mut String);
+
+const PARSE_IMPLS: &[(&str, Parse)] = &[
+    ("parse::<u64>", parse_std),
+    ("scalar", parse_scalar),
+    ("swar", parse_swar),
+];
+
+const FORMAT_IMPLS: &[(&str, Format)] = &[
+    ("to_string", format_to_string),
+    ("write!", format_write),
+    ("lut", format_lut),
+];
+
+/// Random number with exactly `digits` decimal digits.
+fn random_number(digits: u32) -> u64 {
+    let min = if digits == 1 {
+        0
+    } else {
+        10u64.pow(digits - 1)
+    };
+    let max = 10u64.checked_pow(digits).map_or(u64::MAX, |p| p - 1);
+    rand::thread_rng().gen_range(min..=max)
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut numbers: Vec<u64> = vec![0, 9, 10, 99, 100, u64::MAX, u64::MAX - 1];
+    for &digits in DIGIT_COUNTS {
+        numbers.extend((0..1000).map(|_| random_number(digits)));
+    }
+    for digits in 1..=20 {
+        numbers.push(10u64.pow(digits - 1));
+        numbers.push(10u64.checked_pow(digits).map_or(u64::MAX, |p| p - 1));
+    }
+
+    let mut inputs: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    inputs.extend(numbers.iter().map(|n| format!("+{:025}", n)));
+    for s in [
+        "",
+        "+",
+        "-",
+        "-1",
+        "++1",
+        " 1",
+        "1 ",
+        "18446744073709551616",
+        "99999999999999999999",
+        "100000000000000000000",
+    ] {
+        inputs.push(s.to_owned());
+    }
+    // Bytes around `'0'..='9'` are rejected by the SWAR range check.
+    let chars = b"0123456789/:+- a\xff";
+    for _ in 0..100_000 {
+        let len = rng.gen_range(0..=25);
+        let s: String = (0..len)
+            .map(|_| match rng.gen_range(0..10) {
+                0 => chars[rng.gen_range(0..chars.len())] as char,
+                _ => rng.gen_range('0'..='9'),
+            })
+            .collect();
+        inputs.push(s);
+    }
+
+    differential_test(&inputs, |s| s.parse().ok(), PARSE_IMPLS, |f, s| f(s));
+
+    differential_test(
+        &numbers,
+        |n| n.to_string(),
+        FORMAT_IMPLS,
+        |f, &n| {
+            let mut out = String::new();
+            f(n, &mut out);
+            out
+        },
+    );
+}
+
+fn main() {
+    test();
+
+    let numbers: Vec<Vec<u64>> = DIGIT_COUNTS
+        .iter()
+        .map(|&digits| (0..1_000).map(|_| random_number(digits)).collect())
+        .collect();
+    let strings: Vec<Vec<String>> = numbers
+        .iter()
+        .map(|numbers| numbers.iter().map(|n| n.to_string()).collect())
+        .collect();
+    let mut benchmarks = Vec::new();
+    for ((&digits, numbers), strings) in DIGIT_COUNTS.iter().zip(&numbers).zip(&strings) {
+        for &(name, f) in PARSE_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("digits={} parse {}", digits, name),
+                move || {
+                    for s in strings {
+                        hint::black_box(f(s));
+                    }
+                },
+            ));
+        }
+        for &(name, f) in FORMAT_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("digits={} format {}", digits, name),
+                move || {
+                    let mut out = String::with_capacity(20);
+                    for &n in numbers {
+                        out.clear();
+                        f(n, &mut out);
+                        hint::black_box(&out);
+                    }
+                },
+            ));
+        }
+    }
+    rust_str_bench::benchmark(1_000, &benchmarks);
+}
@@ -0,0 +1,209 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::hint;
+use std::rc::Rc;
+
+use fnv::FnvHasher;
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::interner::HashMapInterner;
+use rust_str_bench::interner::Interner;
+use rust_str_bench::interner::LenBucketedInterner;
+use rust_str_bench::interner::RawTableInterner;
+use rust_str_bench::interner::Xxh3BuildHasher;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+/// Number of operations in each benchmark.
+const OPS: usize = 10_000;
+
+const DUP_RATIOS: &[f64] = &[0.5, 0.9, 0.99];
+
+type FnvBuildHasher = BuildHasherDefault<FnvHasher>;
+type FnvHashMapInterner = HashMapInterner<FnvBuildHasher>;
+type Xxh3HashMapInterner = HashMapInterner<Xxh3BuildHasher>;
+
+fn random_identifier() -> String {
+    random_string(rand::thread_rng().gen_range(4..=40))
+}
+
+/// `count` distinct strings.
+fn gen_vocabulary(count: usize) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut strings = Vec::new();
+    while strings.len() < count {
+        let s = random_identifier();
+        if seen.insert(s.clone()) {
+            strings.push(s);
+        }
+    }
+    strings
+}
+
+/// Each string repeats a uniformly chosen earlier string with probability `dup_ratio`.
+fn gen_stream(count: usize, dup_ratio: f64, new_string: impl Fn() -> String) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    let mut stream: Vec<String> = Vec::with_capacity(count);
+    for _ in 0..count {
+        let s = if !stream.is_empty() && rng.gen_bool(dup_ratio) {
+            stream[rng.gen_range(0..stream.len())].clone()
+        } else {
+            new_string()
+        };
+        stream.push(s);
+    }
+    stream
+}
+
+type FirstOccurrences = fn(&Vec<String>) -> Vec<usize>;
+
+/// Intern `strings` into a fresh interner and check `resolve`.
+fn intern_all<I: Interner>(strings: &[String]) -> Vec<u32> {
+    let mut interner = I::default();
+    let ids: Vec<u32> = strings.iter().map(|s| interner.intern(s)).collect();
+    for (s, &id) in strings.iter().zip(&ids) {
+        assert_eq!(interner.resolve(id), s);
+    }
+    ids
+}
+
+/// Replace each id with the index of its first occurrence,
+/// so different id schemes can be compared.
+fn first_occurrences(ids: &[u32]) -> Vec<usize> {
+    let mut first = HashMap::new();
+    ids.iter()
+        .enumerate()
+        .map(|(i, &id)| *first.entry(id).or_insert(i))
+        .collect()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<Vec<String>> = (0..1_000)
+        .map(|_| {
+            let dup_ratio = DUP_RATIOS[rng.gen_range(0..DUP_RATIOS.len())];
+            // Lengths cross the bucketed limit, short strings collide often.
+            gen_stream(rng.gen_range(0..=200), dup_ratio, || {
+                random_string(rand::thread_rng().gen_range(0..=40))
+            })
+        })
+        .collect();
+    let impls: &[(&str, FirstOccurrences)] = &[
+        ("HashMap", |s| {
+            first_occurrences(&intern_all::<HashMapInterner>(s))
+        }),
+        ("RawTable", |s| {
+            first_occurrences(&intern_all::<RawTableInterner>(s))
+        }),
+        ("LenBucketed", |s| {
+            first_occurrences(&intern_all::<LenBucketedInterner>(s))
+        }),
+        ("RawTable<Fnv>", |s| {
+            first_occurrences(&intern_all::<RawTableInterner<FnvBuildHasher>>(s))
+        }),
+        ("LenBucketed<Fnv>", |s| {
+            first_occurrences(&intern_all::<LenBucketedInterner<FnvBuildHasher>>(s))
+        }),
+    ];
+    differential_test(
+        &inputs,
+        |strings| {
+            strings
+                .iter()
+                .map(|s| strings.iter().position(|t| t == s).unwrap())
+                .collect()
+        },
+        impls,
+        |f, s| f(s),
+    );
+}
+
+struct Workload {
+    /// Distinct strings.
+    vocabulary: Vec<String>,
+    streams: Vec<(f64, Vec<String>)>,
+}
+
+fn bm_interner<'a, I: Interner + 'a>(name: &str, workload: &'a Workload) -> Vec<Benchmark<'a>> {
+    let vocabulary = &workload.vocabulary;
+    let mut populated = I::default();
+    let ids: Vec<u32> = vocabulary.iter().map(|s| populated.intern(s)).collect();
+    let populated = Rc::new(RefCell::new(populated));
+    // Resolve ids in random order.
+    let mut resolve_ids = ids;
+    for i in 0..resolve_ids.len() {
+        resolve_ids.swap(i, rand::thread_rng().gen_range(0..=i));
+    }
+
+    let mut benchmarks = vec![Benchmark::new(&format!("{} miss", name), move || {
+        let mut interner = I::default();
+        for s in vocabulary {
+            hint::black_box(interner.intern(s));
+        }
+        interner
+    })];
+    let hit_populated = populated.clone();
+    benchmarks.push(Benchmark::new(&format!("{} hit", name), move || {
+        let mut interner = hit_populated.borrow_mut();
+        for s in vocabulary {
+            hint::black_box(interner.intern(s));
+        }
+    }));
+    benchmarks.push(Benchmark::new(&format!("{} resolve", name), move || {
+        let interner = populated.borrow();
+        for &id in &resolve_ids {
+            hint::black_box(interner.resolve(id));
+        }
+    }));
+    for (dup_ratio, stream) in &workload.streams {
+        benchmarks.push(Benchmark::new(
+            &format!("{} stream dup={}%", name, dup_ratio * 100.0),
+            move || {
+                let mut interner = I::default();
+                for s in stream {
+                    hint::black_box(interner.intern(s));
+                }
+                interner
+            },
+        ));
+    }
+    benchmarks
+}
+
+fn main() {
+    test();
+
+    let workload = Workload {
+        vocabulary: gen_vocabulary(OPS),
+        streams: DUP_RATIOS
+            .iter()
+            .map(|&dup_ratio| (dup_ratio, gen_stream(OPS, dup_ratio, random_identifier)))
+            .collect(),
+    };
+    let mut benchmarks = Vec::new();
+    benchmarks.extend(bm_interner::<HashMapInterner>(
+        "HashMap<SipHash>",
+        &workload,
+    ));
+    benchmarks.extend(bm_interner::<FnvHashMapInterner>("HashMap<Fnv>", &workload));
+    benchmarks.extend(bm_interner::<Xxh3HashMapInterner>(
+        "HashMap<Xxh3>",
+        &workload,
+    ));
+    benchmarks.extend(bm_interner::<RawTableInterner>("RawTable<Xxh3>", &workload));
+    benchmarks.extend(bm_interner::<RawTableInterner<FnvBuildHasher>>(
+        "RawTable<Fnv>",
+        &workload,
+    ));
+    benchmarks.extend(bm_interner::<LenBucketedInterner>(
+        "LenBucketed<Xxh3>",
+        &workload,
+    ));
+    benchmarks.extend(bm_interner::<LenBucketedInterner<FnvBuildHasher>>(
+        "LenBucketed<Fnv>",
+        &workload,
+    ));
+    rust_str_bench::benchmark(OPS, &benchmarks);
+}
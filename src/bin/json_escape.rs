@@ -0,0 +1,270 @@
+#![feature(portable_simd)]
+
+use std::hint;
+use std::simd::u8x16;
+use std::simd::SimdPartialEq;
+use std::simd::SimdPartialOrd;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::mixed_strings;
+use rust_str_bench::Benchmark;
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+
+/// Bytes which must be escaped in a JSON string.
+#[inline]
+const fn is_special(b: u8) -> bool {
+    b == b'"' || b == b'\\' || b < 0x20
+}
+
+/// `is_special` for every byte value.
+const SPECIAL: [bool; 256] = {
+    let mut table = [false; 256];
+    let mut b = 0;
+    while b < 256 {
+        table[b] = is_special(b as u8);
+        b += 1;
+    }
+    table
+};
+
+/// Append escape sequence for special ASCII byte `b`.
+#[inline]
+fn push_escaped(out: &mut String, b: u8) {
+    match b {
+        b'"' => out.push_str("\\\""),
+        b'\\' => out.push_str("\\\\"),
+        b'\n' => out.push_str("\\n"),
+        b'\r' => out.push_str("\\r"),
+        b'\t' => out.push_str("\\t"),
+        0x08 => out.push_str("\\b"),
+        0x0c => out.push_str("\\f"),
+        _ => {
+            out.push_str("\\u00");
+            out.push(HEX[(b >> 4) as usize] as char);
+            out.push(HEX[(b & 0xf) as usize] as char);
+        }
+    }
+}
+
+#[inline(never)]
+fn escape_char_by_char(s: &str, out: &mut String) {
+    for c in s.chars() {
+        if c.is_ascii() && is_special(c as u8) {
+            push_escaped(out, c as u8);
+        } else {
+            out.push(c);
+        }
+    }
+}
+
+/// Check 16 bytes at a time whether `bytes` contains a control byte.
+fn has_control(bytes: &[u8]) -> bool {
+    type V = u8x16;
+    let mut chunks = bytes.chunks_exact(V::LANES);
+    chunks.any(|c| V::from_slice(c).simd_lt(V::splat(0x20)).any())
+        || chunks.remainder().iter().any(|&b| b < 0x20)
+}
+
+/// Find the next quote or backslash with `memchr2` and copy the segment before it at once.
+/// Control bytes are rare, so one vectorized pass checks for them up front
+/// and only strings containing them fall back to a table lookup per byte.
+#[inline(never)]
+fn escape_memchr(s: &str, out: &mut String) {
+    let bytes = s.as_bytes();
+    let find_next: fn(&[u8]) -> Option<usize> = if has_control(bytes) {
+        |rest| rest.iter().position(|&b| SPECIAL[b as usize])
+    } else {
+        |rest| memchr::memchr2(b'"', b'\\', rest)
+    };
+    let mut start = 0;
+    while start < bytes.len() {
+        let rest = &bytes[start..];
+        let next = find_next(rest).unwrap_or(rest.len());
+        // Special bytes are ASCII, so segment ends are char boundaries.
+        out.push_str(&s[start..start + next]);
+        if next == rest.len() {
+            break;
+        }
+        push_escaped(out, rest[next]);
+        start += next + 1;
+    }
+}
+
+/// Classify 16 bytes at a time, copy up to the first special byte.
+#[inline(never)]
+fn escape_simd(s: &str, out: &mut String) {
+    type V = u8x16;
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+    while i + V::LANES <= bytes.len() {
+        let chunk = V::from_slice(&bytes[i..]);
+        let special = chunk.simd_eq(V::splat(b'"'))
+            | chunk.simd_eq(V::splat(b'\\'))
+            | chunk.simd_lt(V::splat(0x20));
+        let mut mask = special.to_bitmask();
+        while mask != 0 {
+            let j = i + mask.trailing_zeros() as usize;
+            out.push_str(&s[start..j]);
+            push_escaped(out, bytes[j]);
+            start = j + 1;
+            mask &= mask - 1;
+        }
+        i += V::LANES;
+    }
+    for j in i..bytes.len() {
+        if is_special(bytes[j]) {
+            out.push_str(&s[start..j]);
+            push_escaped(out, bytes[j]);
+            start = j + 1;
+        }
+    }
+    out.push_str(&s[start..]);
+}
+
+fn parse_hex_4(s: &str) -> Option<u32> {
+    if s.len() != 4 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(s, 16).ok()
+}
+
+/// Inverse of escaping, also accepts `\/` and surrogate pairs.
+/// Returns `None` for invalid escapes and unescaped special bytes.
+fn unescape(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        if c != '\\' {
+            if c.is_ascii() && is_special(c as u8) {
+                return None;
+            }
+            out.push(c);
+            continue;
+        }
+        let (_, e) = chars.next()?;
+        out.push(match e {
+            '"' => '"',
+            '\\' => '\\',
+            '/' => '/',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            'u' => {
+                let hi = parse_hex_4(s.get(i + 2..i + 6)?)?;
+                chars.nth(3);
+                let code = if (0xd800..0xdc00).contains(&hi) {
+                    if s.get(i + 6..i + 8)? != "\\u" {
+                        return None;
+                    }
+                    let lo = parse_hex_4(s.get(i + 8..i + 12)?)?;
+                    if !(0xdc00..0xe000).contains(&lo) {
+                        return None;
+                    }
+                    chars.nth(5);
+                    0x10000 + ((hi - 0xd800) << 10) + (lo - 0xdc00)
+                } else {
+                    hi
+                };
+                char::from_u32(code)?
+            }
+            _ => return None,
+        });
+    }
+    Some(out)
+}
+
+type Escape = fn(&str, &mut String);
+
+const IMPLS: &[(&str, Escape)] = &[
+    ("char_by_char", escape_char_by_char),
+    ("memchr", escape_memchr),
+    ("simd", escape_simd),
+];
+
+/// Specials used in generated inputs, mixed shorthand and `\u00XX` escapes.
+const SPECIALS: &[char] = &['"', '\\', '\n', '\t', '\x01', '\x1f'];
+
+/// Lowercase text where each char is special with probability `special_ratio`.
+fn gen_text(len: usize, special_ratio: f64) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| {
+            if rng.gen_bool(special_ratio) {
+                SPECIALS[rng.gen_range(0..SPECIALS.len())]
+            } else {
+                rng.gen_range('a'..='z')
+            }
+        })
+        .collect()
+}
+
+fn escape_to_string(f: Escape, s: &str) -> String {
+    let mut out = String::new();
+    f(s, &mut out);
+    out
+}
+
+fn test() {
+    let inputs = mixed_strings(10_000, 60, |len| gen_text(len, 0.3));
+    differential_test(
+        &inputs,
+        |s| escape_to_string(escape_char_by_char, s),
+        IMPLS,
+        |&f, s| escape_to_string(f, s),
+    );
+
+    for s in &inputs {
+        let escaped = escape_to_string(escape_simd, s);
+        assert!(!escaped.bytes().any(|b| b < 0x20), "{:?}", escaped);
+        assert_eq!(unescape(&escaped).as_deref(), Some(s.as_str()));
+    }
+    assert_eq!(
+        unescape("\\/\\u00e9\\ud83d\\ude00").as_deref(),
+        Some("/é😀")
+    );
+    for invalid in [
+        "\\",
+        "\\x",
+        "\\u12",
+        "\\u12g4",
+        "\\ud83d",
+        "\\ud83d\\u0041",
+        "\n",
+        "\"",
+    ] {
+        assert_eq!(unescape(invalid), None, "{:?}", invalid);
+    }
+    println!("Round-trip tested on {} inputs", inputs.len());
+}
+
+fn main() {
+    test();
+
+    for special_ratio in [0.0, 0.01, 0.1] {
+        let strings: Vec<String> = (0..100).map(|_| gen_text(1000, special_ratio)).collect();
+        let strings = &strings;
+        let total_bytes: usize = strings.iter().map(|s| s.len()).sum();
+        println!("special={}%", special_ratio * 100.0);
+        let benchmarks: Vec<Benchmark> = IMPLS
+            .iter()
+            .map(|&(name, f)| {
+                Benchmark::new(name, move || {
+                    let mut out = String::with_capacity(2 * 1000);
+                    for s in strings {
+                        out.clear();
+                        f(s, &mut out);
+                        hint::black_box(&out);
+                    }
+                })
+            })
+            .collect();
+        let seconds = rust_str_bench::benchmark(strings.len(), &benchmarks);
+        rust_str_bench::print_throughput(&benchmarks, &seconds, &[("bytes", total_bytes as f64)]);
+    }
+}
@@ -0,0 +1,207 @@
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::hint;
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use fnv::FnvHasher;
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::small_string::ArenaStr;
+use rust_str_bench::small_string::SmallString;
+use rust_str_bench::small_string::StrArena;
+use rust_str_bench::Benchmark;
+
+/// Inline, just above inline capacity, and long.
+const LEN_RANGES: &[RangeInclusive<usize>] = &[0..=23, 24..=32, 33..=99];
+
+fn hash_with<H: Hasher + Default, T: Hash + ?Sized>(v: &T) -> u64 {
+    let mut hasher = H::default();
+    v.hash(&mut hasher);
+    hasher.finish()
+}
+
+type HashFn<T> = fn(&T) -> u64;
+type Observe = fn(&String) -> Observed;
+
+/// Hashers from `str_hash_random`.
+fn hashers<T: Hash + ?Sized>() -> [(&'static str, HashFn<T>); 3] {
+    [
+        ("DefaultHasher", hash_with::<DefaultHasher, T>),
+        ("FnvHasher", hash_with::<FnvHasher, T>),
+        ("xxh3::Xxh3", hash_with::<xxhash_rust::xxh3::Xxh3, T>),
+    ]
+}
+
+/// Construct, clone, hash and compare to an equal copy.
+fn bm_type<'a, T: Clone + Hash + Eq + 'a>(
+    name: &str,
+    from_str: fn(&str) -> T,
+    strings: &'a [String],
+) -> Vec<Benchmark<'a>> {
+    let values: Rc<Vec<T>> = Rc::new(strings.iter().map(|s| from_str(s)).collect());
+    let copies: Vec<T> = strings.iter().map(|s| from_str(s)).collect();
+    let mut benchmarks = vec![Benchmark::new(&format!("{} construct", name), move || {
+        for s in strings {
+            hint::black_box(from_str(s));
+        }
+    })];
+    let clone_values = values.clone();
+    benchmarks.push(Benchmark::new(&format!("{} clone", name), move || {
+        for v in clone_values.iter() {
+            hint::black_box(v.clone());
+        }
+    }));
+    for (hasher_name, hash) in hashers::<T>() {
+        let values = values.clone();
+        benchmarks.push(Benchmark::new(
+            &format!("{} hash {}", name, hasher_name),
+            move || {
+                for v in values.iter() {
+                    hint::black_box(hash(v));
+                }
+            },
+        ));
+    }
+    benchmarks.push(Benchmark::new(&format!("{} eq", name), move || {
+        for (a, b) in values.iter().zip(&copies) {
+            hint::black_box(a == b);
+        }
+    }));
+    benchmarks
+}
+
+/// Same operations on arena handles: construction appends to a cleared arena,
+/// clone copies the handle, hash and eq read strings from the arena.
+fn bm_arena<'a>(strings: &'a [String]) -> Vec<Benchmark<'a>> {
+    let mut arena = StrArena::default();
+    let handles: Vec<ArenaStr> = strings.iter().map(|s| arena.alloc(s)).collect();
+    let copies: Vec<ArenaStr> = strings.iter().map(|s| arena.alloc(s)).collect();
+    let arena = Rc::new(arena);
+    let handles = Rc::new(handles);
+
+    let construct_arena = RefCell::new(StrArena::default());
+    let mut benchmarks = vec![Benchmark::new("StrArena construct", move || {
+        let mut arena = construct_arena.borrow_mut();
+        arena.clear();
+        for s in strings {
+            hint::black_box(arena.alloc(s));
+        }
+    })];
+    let clone_handles = handles.clone();
+    benchmarks.push(Benchmark::new("StrArena clone", move || {
+        for h in clone_handles.iter() {
+            hint::black_box(*h);
+        }
+    }));
+    for (hasher_name, hash) in hashers::<str>() {
+        let (arena, handles) = (arena.clone(), handles.clone());
+        benchmarks.push(Benchmark::new(
+            &format!("StrArena hash {}", hasher_name),
+            move || {
+                for &h in handles.iter() {
+                    hint::black_box(hash(arena.get(h)));
+                }
+            },
+        ));
+    }
+    benchmarks.push(Benchmark::new("StrArena eq", move || {
+        for (&a, &b) in handles.iter().zip(&copies) {
+            hint::black_box(arena.get(a) == arena.get(b));
+        }
+    }));
+    benchmarks
+}
+
+#[derive(Debug, PartialEq)]
+struct Observed {
+    value: String,
+    clone: String,
+    /// Whether the value is equal to a separately constructed copy.
+    eq_copy: bool,
+    hashes: [u64; 3],
+}
+
+fn observe<T: Clone + Hash + Eq>(
+    s: &str,
+    from_str: fn(&str) -> T,
+    as_str: fn(&T) -> &str,
+) -> Observed {
+    let v = from_str(s);
+    let clone = v.clone();
+    let hashes = hashers::<T>().map(|(_, hash)| hash(&v));
+    Observed {
+        value: as_str(&v).to_owned(),
+        clone: as_str(&clone).to_owned(),
+        eq_copy: v == from_str(s),
+        hashes,
+    }
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<String> = (0..10_000)
+        .map(|_| random_utf8_string(rng.gen_range(0..=40)))
+        .chain((0..=100).map(random_string))
+        .collect();
+    let impls: &[(&str, Observe)] = &[
+        ("Box<str>", |s| {
+            observe(s, |s| Box::<str>::from(s), |b| &**b)
+        }),
+        ("SmallString", |s| {
+            observe(s, SmallString::new, SmallString::as_str)
+        }),
+        ("StrArena", |s| {
+            let mut arena = StrArena::default();
+            let h = arena.alloc(s);
+            let copy = arena.alloc(s);
+            Observed {
+                value: arena.get(h).to_owned(),
+                // Handles are `Copy`.
+                clone: arena.get(h).to_owned(),
+                eq_copy: arena.get(h) == arena.get(copy),
+                hashes: hashers::<str>().map(|(_, hash)| hash(arena.get(h))),
+            }
+        }),
+    ];
+    differential_test(
+        &inputs,
+        |s| observe(s, |s| s.to_owned(), |s| s.as_str()),
+        impls,
+        |f, s| f(s),
+    );
+    for s in &inputs {
+        assert_eq!(SmallString::new(s).is_inline(), s.len() <= 23);
+    }
+    // Inline and heap strings with equal prefixes are not equal.
+    assert!(SmallString::new("a") != SmallString::new("a\0"));
+    assert!(SmallString::new(&"a".repeat(23)) != SmallString::new(&"a".repeat(24)));
+}
+
+fn main() {
+    test();
+
+    let mut rng = rand::thread_rng();
+    let inputs: Vec<(&RangeInclusive<usize>, Vec<String>)> = LEN_RANGES
+        .iter()
+        .map(|len| {
+            let strings = (0..1_000)
+                .map(|_| random_string(rng.gen_range(len.clone())))
+                .collect();
+            (len, strings)
+        })
+        .collect();
+    for (len, strings) in &inputs {
+        println!("len={:?}", len);
+        let mut benchmarks = Vec::new();
+        benchmarks.extend(bm_type("String", |s| s.to_owned(), strings));
+        benchmarks.extend(bm_type("Box<str>", |s| Box::<str>::from(s), strings));
+        benchmarks.extend(bm_type("SmallString", SmallString::new, strings));
+        benchmarks.extend(bm_arena(strings));
+        rust_str_bench::benchmark(strings.len(), &benchmarks);
+    }
+}
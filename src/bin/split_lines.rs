@@ -0,0 +1,196 @@
+#![feature(portable_simd)]
+
+use std::simd::u8x32;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::Benchmark;
+
+/// Size of generated text for each line length distribution.
+const TEXT_LEN: usize = 1 << 20;
+
+/// Distribution of line lengths in generated text, newline not included.
+#[derive(Copy, Clone, Debug)]
+enum LineLengths {
+    Fixed(usize),
+    Uniform(usize, usize),
+    /// Mostly short lines with occasional long ones.
+    Exponential {
+        mean: f64,
+    },
+}
+
+impl LineLengths {
+    fn sample(self, rng: &mut impl Rng) -> usize {
+        match self {
+            LineLengths::Fixed(len) => len,
+            LineLengths::Uniform(min, max) => rng.gen_range(min..=max),
+            LineLengths::Exponential { mean } => (-mean * (1.0 - rng.gen::<f64>()).ln()) as usize,
+        }
+    }
+}
+
+const LINE_LENGTHS: &[LineLengths] = &[
+    LineLengths::Fixed(8),
+    LineLengths::Uniform(0, 80),
+    LineLengths::Uniform(40, 120),
+    LineLengths::Exponential { mean: 200.0 },
+];
+
+/// Newline-terminated lines of lowercase letters, at least `len` bytes.
+fn gen_text(line_lengths: LineLengths, len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let mut text = String::with_capacity(len + 1000);
+    while text.len() < len {
+        text.push_str(&random_string(line_lengths.sample(&mut rng)));
+        text.push('\n');
+    }
+    text
+}
+
+/// Fold line offset and length into a checksum, so all implementations
+/// do the same work per line and results can be compared.
+#[inline]
+fn fold_line(checksum: u64, text: &str, line: &str) -> u64 {
+    let offset = line.as_ptr() as usize - text.as_ptr() as usize;
+    checksum
+        .wrapping_mul(0x100000001b3)
+        .wrapping_add(((offset as u64) << 32) | line.len() as u64)
+}
+
+/// Implementations follow `split_terminator('\n')`: no empty line after the final newline.
+/// Text contains no `'\r'`, so `lines()` agrees.
+#[inline(never)]
+fn lines(text: &str) -> u64 {
+    text.lines().fold(0, |h, line| fold_line(h, text, line))
+}
+
+#[inline(never)]
+fn split_char(text: &str) -> u64 {
+    if text.is_empty() {
+        return 0;
+    }
+    text.strip_suffix('\n')
+        .unwrap_or(text)
+        .split('\n')
+        .fold(0, |h, line| fold_line(h, text, line))
+}
+
+#[inline(never)]
+fn split_terminator(text: &str) -> u64 {
+    text.split_terminator('\n')
+        .fold(0, |h, line| fold_line(h, text, line))
+}
+
+#[inline(never)]
+fn split_memchr(text: &str) -> u64 {
+    let mut checksum = 0;
+    let mut start = 0;
+    for end in memchr::memchr_iter(b'\n', text.as_bytes()) {
+        checksum = fold_line(checksum, text, &text[start..end]);
+        start = end + 1;
+    }
+    if start < text.len() {
+        checksum = fold_line(checksum, text, &text[start..]);
+    }
+    checksum
+}
+
+/// Iterate set bits of 32-byte newline masks.
+#[inline(never)]
+fn split_simd_mask(text: &str) -> u64 {
+    type V = u8x32;
+    let bytes = text.as_bytes();
+    let newline = V::splat(b'\n');
+    let mut checksum = 0;
+    let mut start = 0;
+    let mut i = 0;
+    while i + V::LANES <= bytes.len() {
+        let mut mask = V::from_slice(&bytes[i..]).simd_eq(newline).to_bitmask();
+        while mask != 0 {
+            let end = i + mask.trailing_zeros() as usize;
+            checksum = fold_line(checksum, text, &text[start..end]);
+            start = end + 1;
+            mask &= mask - 1;
+        }
+        i += V::LANES;
+    }
+    for end in i..bytes.len() {
+        if bytes[end] == b'\n' {
+            checksum = fold_line(checksum, text, &text[start..end]);
+            start = end + 1;
+        }
+    }
+    if start < text.len() {
+        checksum = fold_line(checksum, text, &text[start..]);
+    }
+    checksum
+}
+
+type Split = fn(&str) -> u64;
+
+const IMPLS: &[(&str, Split)] = &[
+    ("lines", lines),
+    ("split('\\n')", split_char),
+    ("split_terminator", split_terminator),
+    ("memchr", split_memchr),
+    ("simd_mask", split_simd_mask),
+];
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs: Vec<String> = Vec::new();
+    for _ in 0..10_000 {
+        let len = rng.gen_range(0..=100);
+        let s: String = (0..len)
+            .map(|_| match rng.gen_range(0..4) {
+                0 => '\n',
+                _ => random_utf8_string(1).chars().next().unwrap(),
+            })
+            .filter(|&c| c != '\r')
+            .collect();
+        inputs.push(s);
+    }
+    for &line_lengths in LINE_LENGTHS {
+        inputs.push(gen_text(line_lengths, 10_000));
+    }
+    differential_test(
+        &inputs,
+        |s| {
+            s.split_terminator('\n')
+                .fold(0, |h, line| fold_line(h, s, line))
+        },
+        IMPLS,
+        |f, s| f(s),
+    );
+}
+
+fn main() {
+    test();
+
+    for &line_lengths in LINE_LENGTHS {
+        let text = gen_text(line_lengths, TEXT_LEN);
+        let n_lines = text.split_terminator('\n').count();
+        println!(
+            "{:?}: {} lines, {} bytes",
+            line_lengths,
+            n_lines,
+            text.len()
+        );
+        let text = &text;
+        let benchmarks: Vec<_> = IMPLS
+            .iter()
+            .map(|&(name, f)| Benchmark::new(name, move || f(text)))
+            .collect();
+        let seconds = rust_str_bench::benchmark(1, &benchmarks);
+        rust_str_bench::print_throughput(
+            &benchmarks,
+            &seconds,
+            &[("lines", n_lines as f64), ("bytes", text.len() as f64)],
+        );
+    }
+}
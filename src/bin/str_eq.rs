@@ -0,0 +1,236 @@
+use std::cmp;
+use std::hint;
+use std::ops::RangeInclusive;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::Benchmark;
+
+#[inline]
+fn load_u32(s: &[u8], i: usize) -> u32 {
+    u32::from_ne_bytes(s[i..i + 4].try_into().unwrap())
+}
+
+#[inline]
+fn load_u64(s: &[u8], i: usize) -> u64 {
+    u64::from_ne_bytes(s[i..i + 8].try_into().unwrap())
+}
+
+#[inline]
+fn load_u128(s: &[u8], i: usize) -> u128 {
+    u128::from_ne_bytes(s[i..i + 16].try_into().unwrap())
+}
+
+/// Compare bytes of equal length slices with two possibly overlapping loads
+/// from the start and from the end, 16-byte loop for longer slices.
+#[inline]
+fn bytes_eq_overlapping(a: &[u8], b: &[u8]) -> bool {
+    debug_assert_eq!(a.len(), b.len());
+    let n = a.len();
+    match n {
+        0 => true,
+        1..=3 => a[0] == b[0] && a[n / 2] == b[n / 2] && a[n - 1] == b[n - 1],
+        4..=7 => (load_u32(a, 0) ^ load_u32(b, 0)) | (load_u32(a, n - 4) ^ load_u32(b, n - 4)) == 0,
+        8..=16 => {
+            (load_u64(a, 0) ^ load_u64(b, 0)) | (load_u64(a, n - 8) ^ load_u64(b, n - 8)) == 0
+        }
+        _ => {
+            let mut i = 0;
+            while i + 16 < n {
+                if load_u128(a, i) != load_u128(b, i) {
+                    return false;
+                }
+                i += 16;
+            }
+            load_u128(a, n - 16) == load_u128(b, n - 16)
+        }
+    }
+}
+
+#[inline]
+fn bytes_eq_memcmp(a: &[u8], b: &[u8]) -> bool {
+    debug_assert_eq!(a.len(), b.len());
+    // Pointers of empty slices are dangling.
+    if a.is_empty() {
+        return true;
+    }
+    unsafe { libc::memcmp(a.as_ptr() as *const _, b.as_ptr() as *const _, a.len()) == 0 }
+}
+
+#[inline(never)]
+fn eq_operator(a: &str, b: &str) -> bool {
+    a == b
+}
+
+#[inline(never)]
+fn eq_memcmp(a: &str, b: &str) -> bool {
+    a.len() == b.len() && bytes_eq_memcmp(a.as_bytes(), b.as_bytes())
+}
+
+/// Most short identifiers differ in length or in the first 8 bytes.
+#[inline(never)]
+fn eq_len_first_word(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    if a.len() < 8 {
+        return a == b;
+    }
+    load_u64(a, 0) == load_u64(b, 0) && a[8..] == b[8..]
+}
+
+#[inline(never)]
+fn eq_overlapping_loads(a: &str, b: &str) -> bool {
+    a.len() == b.len() && bytes_eq_overlapping(a.as_bytes(), b.as_bytes())
+}
+
+#[inline(never)]
+fn starts_with(s: &str, prefix: &str) -> bool {
+    s.starts_with(prefix)
+}
+
+#[inline(never)]
+fn starts_with_memcmp(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len() && bytes_eq_memcmp(&s.as_bytes()[..prefix.len()], prefix.as_bytes())
+}
+
+#[inline(never)]
+fn starts_with_overlapping_loads(s: &str, prefix: &str) -> bool {
+    s.len() >= prefix.len()
+        && bytes_eq_overlapping(&s.as_bytes()[..prefix.len()], prefix.as_bytes())
+}
+
+type Compare = fn(&str, &str) -> bool;
+
+const EQ_IMPLS: &[(&str, Compare)] = &[
+    ("==", eq_operator),
+    ("memcmp", eq_memcmp),
+    ("len_first_word", eq_len_first_word),
+    ("overlapping_loads", eq_overlapping_loads),
+];
+
+const STARTS_WITH_IMPLS: &[(&str, Compare)] = &[
+    ("starts_with", starts_with),
+    ("starts_with memcmp", starts_with_memcmp),
+    (
+        "starts_with overlapping_loads",
+        starts_with_overlapping_loads,
+    ),
+];
+
+#[derive(Copy, Clone, Debug)]
+enum Difference {
+    Equal,
+    /// Strings differ in the first byte.
+    Early,
+    /// Strings differ in the last byte.
+    Late,
+}
+
+/// Copy of `s` with byte `i` replaced by a different lowercase letter.
+fn with_byte_changed(s: &str, i: usize) -> String {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes[i] = if bytes[i] == b'z' { b'a' } else { b'z' };
+    String::from_utf8(bytes).unwrap()
+}
+
+/// Pairs of lowercase strings with the same length in `len`.
+fn gen_pairs(
+    count: usize,
+    len: RangeInclusive<usize>,
+    difference: Difference,
+) -> Vec<(String, String)> {
+    let mut rng = rand::thread_rng();
+    // A byte can only be changed in a non-empty string.
+    let len = match difference {
+        Difference::Equal => len,
+        _ => cmp::max(*len.start(), 1)..=*len.end(),
+    };
+    (0..count)
+        .map(|_| {
+            let a = random_string(rng.gen_range(len.clone()));
+            let b = match difference {
+                Difference::Equal => a.clone(),
+                Difference::Early => with_byte_changed(&a, 0),
+                Difference::Late => with_byte_changed(&a, a.len() - 1),
+            };
+            (a, b)
+        })
+        .collect()
+}
+
+/// Pairs of (`b` with random suffix, `a`), so `a` is a prefix of the first string
+/// if `a` and `b` are equal.
+fn to_prefix_pairs(pairs: &[(String, String)]) -> Vec<(String, String)> {
+    let mut rng = rand::thread_rng();
+    pairs
+        .iter()
+        .map(|(a, b)| (b.clone() + &random_string(rng.gen_range(0..=16)), a.clone()))
+        .collect()
+}
+
+fn test_impls(
+    inputs: &[(String, String)],
+    reference: fn(&str, &str) -> bool,
+    impls: &[(&str, Compare)],
+) {
+    differential_test(inputs, |(a, b)| reference(a, b), impls, |f, (a, b)| f(a, b));
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs = Vec::new();
+    for difference in [Difference::Equal, Difference::Early, Difference::Late] {
+        inputs.extend(gen_pairs(10_000, 0..=64, difference));
+    }
+    // Differences at every position.
+    for len in 1..=64 {
+        let a = random_string(len);
+        for i in 0..len {
+            inputs.push((a.clone(), with_byte_changed(&a, i)));
+        }
+    }
+    for _ in 0..10_000 {
+        inputs.push((
+            random_utf8_string(rng.gen_range(0..=8)),
+            random_utf8_string(rng.gen_range(0..=8)),
+        ));
+    }
+    test_impls(&inputs, |a, b| a == b, EQ_IMPLS);
+    inputs.extend(to_prefix_pairs(&inputs));
+    test_impls(&inputs, |s, p| s.starts_with(p), STARTS_WITH_IMPLS);
+}
+
+fn main() {
+    test();
+
+    let lens = [0..=8, 9..=16, 17..=32, 33..=64];
+    let mut inputs = Vec::new();
+    for len in lens {
+        for difference in [Difference::Equal, Difference::Early, Difference::Late] {
+            let name = format!("len={:?} {:?}", len, difference);
+            let pairs = gen_pairs(1_000, len.clone(), difference);
+            let prefix_pairs = to_prefix_pairs(&pairs);
+            inputs.push((name, pairs, prefix_pairs));
+        }
+    }
+    let mut benchmarks = Vec::new();
+    for (name, pairs, prefix_pairs) in &inputs {
+        for (pairs, impls) in [(pairs, EQ_IMPLS), (prefix_pairs, STARTS_WITH_IMPLS)] {
+            for &(impl_name, f) in impls {
+                benchmarks.push(Benchmark::new(
+                    &format!("{} {}", name, impl_name),
+                    move || {
+                        for (a, b) in pairs {
+                            hint::black_box(f(a, b));
+                        }
+                    },
+                ));
+            }
+        }
+    }
+    rust_str_bench::benchmark(1_000, &benchmarks);
+}
@@ -0,0 +1,319 @@
+#![feature(portable_simd)]
+
+use std::cmp;
+use std::hint;
+use std::simd::u8x32;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::benchmark;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+const NEEDLE_LENS: &[usize] = &[1, 2, 4, 8, 16, 32, 64];
+const HAYSTACK_LENS: &[usize] = &[16, 256, 4096, 65536];
+
+/// Haystack bytes per cell, so each cell takes similar time.
+const TOTAL_HAYSTACK_BYTES: usize = 1 << 18;
+
+fn naive(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if haystack.len() < needle.len() {
+        return None;
+    }
+    (0..=haystack.len() - needle.len()).find(|&i| &haystack[i..i + needle.len()] == needle)
+}
+
+/// Rolling polynomial hash modulo 2^64.
+fn rabin_karp(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    const BASE: u64 = 0x100000001b3;
+    let n = needle.len();
+    if haystack.len() < n {
+        return None;
+    }
+    let hash = |s: &[u8]| {
+        s.iter()
+            .fold(0u64, |h, &b| h.wrapping_mul(BASE).wrapping_add(b as u64))
+    };
+    // Weight of the byte leaving the window.
+    let top = (1..n).fold(1u64, |p, _| p.wrapping_mul(BASE));
+    let needle_hash = hash(needle);
+    let mut window_hash = hash(&haystack[..n]);
+    let mut i = 0;
+    loop {
+        if window_hash == needle_hash && &haystack[i..i + n] == needle {
+            return Some(i);
+        }
+        if i + n == haystack.len() {
+            return None;
+        }
+        window_hash = window_hash
+            .wrapping_sub((haystack[i] as u64).wrapping_mul(top))
+            .wrapping_mul(BASE)
+            .wrapping_add(haystack[i + n] as u64);
+        i += 1;
+    }
+}
+
+/// Boyer–Moore–Horspool: shift by the distance from the last occurrence
+/// of the window last byte in the needle to the needle end.
+fn horspool(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let n = needle.len();
+    if n == 0 {
+        return Some(0);
+    }
+    if haystack.len() < n {
+        return None;
+    }
+    let mut shift = [n; 256];
+    for (i, &b) in needle[..n - 1].iter().enumerate() {
+        shift[b as usize] = n - 1 - i;
+    }
+    let mut i = 0;
+    while i + n <= haystack.len() {
+        let last = haystack[i + n - 1];
+        if last == needle[n - 1] && haystack[i..i + n - 1] == needle[..n - 1] {
+            return Some(i);
+        }
+        i += shift[last as usize];
+    }
+    None
+}
+
+/// Start and period of the maximal suffix of `s` by byte order or by reversed byte order.
+///
+/// Adapted from `TwoWaySearcher::maximal_suffix` in `core::str::pattern`.
+fn maximal_suffix(s: &[u8], reversed: bool) -> (usize, usize) {
+    let mut left = 0;
+    let mut right = 1;
+    let mut offset = 0;
+    let mut period = 1;
+    while let Some(&a) = s.get(right + offset) {
+        let b = s[left + offset];
+        if (a < b && !reversed) || (a > b && reversed) {
+            // Suffix is smaller, period is entire prefix so far.
+            right += offset + 1;
+            offset = 0;
+            period = right - left;
+        } else if a == b {
+            if offset + 1 == period {
+                right += offset + 1;
+                offset = 0;
+            } else {
+                offset += 1;
+            }
+        } else {
+            // Suffix is larger, start over from current location.
+            left = right;
+            right += 1;
+            offset = 0;
+            period = 1;
+        }
+    }
+    (left, period)
+}
+
+/// Crochemore–Perrin Two-Way: match the right part of the critical factorization
+/// left to right, then the left part right to left.
+fn two_way(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    let n = needle.len();
+    if n == 0 {
+        return Some(0);
+    }
+    let (crit_pos_false, period_false) = maximal_suffix(needle, false);
+    let (crit_pos_true, period_true) = maximal_suffix(needle, true);
+    let (crit_pos, period) = if crit_pos_false > crit_pos_true {
+        (crit_pos_false, period_false)
+    } else {
+        (crit_pos_true, period_true)
+    };
+    // Periodic needle: remember how much of the left part matched after a shift by period.
+    let periodic = needle[..crit_pos] == needle[period..period + crit_pos];
+    let period = if periodic {
+        period
+    } else {
+        cmp::max(crit_pos, n - crit_pos) + 1
+    };
+
+    let mut pos = 0;
+    let mut memory = 0;
+    while pos + n <= haystack.len() {
+        let mut i = cmp::max(crit_pos, memory);
+        while i < n && needle[i] == haystack[pos + i] {
+            i += 1;
+        }
+        if i < n {
+            pos += i - crit_pos + 1;
+            memory = 0;
+            continue;
+        }
+        let mut j = crit_pos;
+        while j > memory && needle[j - 1] == haystack[pos + j - 1] {
+            j -= 1;
+        }
+        if j <= memory {
+            return Some(pos);
+        }
+        pos += period;
+        if periodic {
+            memory = n - period;
+        }
+    }
+    None
+}
+
+/// Compare 32 positions at once by needle first and last bytes,
+/// then verify candidates.
+fn simd_first_last(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    type V = u8x32;
+    let n = needle.len();
+    if n == 0 {
+        return Some(0);
+    }
+    let first = V::splat(needle[0]);
+    let last = V::splat(needle[n - 1]);
+    let mut i = 0;
+    while i + n - 1 + V::LANES <= haystack.len() {
+        let a = V::from_slice(&haystack[i..]);
+        let b = V::from_slice(&haystack[i + n - 1..]);
+        let mut mask = (a.simd_eq(first) & b.simd_eq(last)).to_bitmask();
+        while mask != 0 {
+            let j = i + mask.trailing_zeros() as usize;
+            if &haystack[j..j + n] == needle {
+                return Some(j);
+            }
+            mask &= mask - 1;
+        }
+        i += V::LANES;
+    }
+    naive(&haystack[i..], needle).map(|pos| pos + i)
+}
+
+fn memmem(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    memchr::memmem::find(haystack, needle)
+}
+
+type FindSubstring = fn(&[u8], &[u8]) -> Option<usize>;
+
+/// All implementations, `naive` first.
+const IMPLS: &[(&str, FindSubstring)] = &[
+    ("naive", naive),
+    ("rabin_karp", rabin_karp),
+    ("horspool", horspool),
+    ("two_way", two_way),
+    ("simd_first_last", simd_first_last),
+    ("memmem", memmem),
+];
+
+#[derive(Debug)]
+struct Input {
+    haystack: Vec<u8>,
+    needle: Vec<u8>,
+}
+
+/// Random bytes from `alphabet`, small alphabets produce many partial matches.
+fn random_bytes(alphabet: &[u8], len: usize) -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+        .collect()
+}
+
+fn test() {
+    let mut rng = rand::thread_rng();
+    let mut inputs = Vec::new();
+    for alphabet in [&b"a"[..], b"ab", b"abc", b"abcdefghijklmnopqrstuvwxyz"] {
+        for _ in 0..10_000 {
+            let haystack = random_bytes(alphabet, rng.gen_range(0..=100));
+            let needle = if !haystack.is_empty() && rng.gen() {
+                let start = rng.gen_range(0..haystack.len());
+                let end = rng.gen_range(start..=haystack.len().min(start + 40));
+                haystack[start..end].to_vec()
+            } else {
+                random_bytes(alphabet, rng.gen_range(0..=10))
+            };
+            inputs.push(Input { haystack, needle });
+        }
+    }
+    // Periodic needles, which need Two-Way memory.
+    for needle in [
+        &b"abab"[..],
+        b"aaab",
+        b"abaaba",
+        b"aabaabaab",
+        b"abcabcabd",
+        b"aabaabaabaabaabaabaab",
+        b"abababababababababababababababababababbb",
+    ] {
+        for _ in 0..1000 {
+            inputs.push(Input {
+                haystack: random_bytes(b"ab", rng.gen_range(0..=100)),
+                needle: needle.to_vec(),
+            });
+            let mut haystack = needle.repeat(rng.gen_range(0..=5));
+            haystack.extend(needle);
+            inputs.push(Input {
+                haystack,
+                needle: needle.to_vec(),
+            });
+        }
+    }
+
+    differential_test(
+        &inputs,
+        |i| {
+            if i.needle.is_empty() {
+                return Some(0);
+            }
+            i.haystack
+                .windows(i.needle.len())
+                .position(|w| w == i.needle)
+        },
+        IMPLS,
+        |f, i| f(&i.haystack, &i.needle),
+    );
+}
+
+/// Haystacks are random lowercase strings, half of needles are taken from the haystack.
+fn gen_inputs(haystack_len: usize, needle_len: usize) -> Vec<Input> {
+    let mut rng = rand::thread_rng();
+    (0..TOTAL_HAYSTACK_BYTES / haystack_len)
+        .map(|_| {
+            let haystack = random_string(haystack_len).into_bytes();
+            let needle = if rng.gen() {
+                let start = rng.gen_range(0..=haystack_len - needle_len);
+                haystack[start..start + needle_len].to_vec()
+            } else {
+                random_string(needle_len).into_bytes()
+            };
+            Input { haystack, needle }
+        })
+        .collect()
+}
+
+fn main() {
+    test();
+
+    for &haystack_len in HAYSTACK_LENS {
+        for &needle_len in NEEDLE_LENS {
+            if needle_len > haystack_len {
+                continue;
+            }
+            println!("haystack_len={} needle_len={}", haystack_len, needle_len);
+            let inputs = gen_inputs(haystack_len, needle_len);
+            let benchmarks: Vec<_> = IMPLS
+                .iter()
+                .map(|&(name, f)| {
+                    let inputs = &inputs;
+                    Benchmark::new(name, move || {
+                        for input in inputs {
+                            hint::black_box(f(&input.haystack, &input.needle));
+                        }
+                    })
+                })
+                .collect();
+            benchmark(inputs.len(), &benchmarks);
+        }
+    }
+}
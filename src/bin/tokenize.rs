@@ -0,0 +1,304 @@
+#![feature(portable_simd)]
+
+use std::cell::RefCell;
+use std::hint;
+use std::simd::u8x32;
+use std::simd::Mask;
+use std::simd::SimdPartialEq;
+use std::simd::SimdPartialOrd;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::mixed_strings;
+use rust_str_bench::random_strings::random_string;
+use rust_str_bench::Benchmark;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum TokenKind {
+    /// `[A-Za-z_][A-Za-z0-9_]*`.
+    Ident,
+    /// `[0-9]+`.
+    Number,
+    /// Any other single ASCII byte except whitespace.
+    Punct,
+    /// Single non-ASCII char.
+    Other,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    start: u32,
+    end: u32,
+}
+
+fn push_token(tokens: &mut Vec<Token>, kind: TokenKind, start: usize, end: usize) {
+    tokens.push(Token {
+        kind,
+        start: start as u32,
+        end: end as u32,
+    });
+}
+
+#[inline(never)]
+fn tokenize_char_match(s: &str, tokens: &mut Vec<Token>) {
+    let mut chars = s.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let kind = match c {
+            ' ' | '\t' | '\n' | '\r' => continue,
+            'A'..='Z' | 'a'..='z' | '_' => {
+                while let Some((_, 'A'..='Z' | 'a'..='z' | '0'..='9' | '_')) = chars.peek() {
+                    chars.next();
+                }
+                TokenKind::Ident
+            }
+            '0'..='9' => {
+                while let Some((_, '0'..='9')) = chars.peek() {
+                    chars.next();
+                }
+                TokenKind::Number
+            }
+            '\0'..='\x7f' => TokenKind::Punct,
+            _ => TokenKind::Other,
+        };
+        let end = chars.peek().map_or(s.len(), |&(i, _)| i);
+        push_token(tokens, kind, start, end);
+    }
+}
+
+// Byte classes.
+const SPACE: u8 = 0;
+const IDENT_START: u8 = 1;
+const DIGIT: u8 = 2;
+const PUNCT: u8 = 3;
+const NON_ASCII: u8 = 4;
+
+const CLASSES: [u8; 256] = {
+    let mut classes = [PUNCT; 256];
+    let mut b = 0;
+    while b < 256 {
+        classes[b] = match b as u8 {
+            b' ' | b'\t' | b'\n' | b'\r' => SPACE,
+            b'A'..=b'Z' | b'a'..=b'z' | b'_' => IDENT_START,
+            b'0'..=b'9' => DIGIT,
+            0x80..=0xff => NON_ASCII,
+            _ => PUNCT,
+        };
+        b += 1;
+    }
+    classes
+};
+
+/// Length of the UTF-8 char starting at `i`.
+#[inline]
+fn char_len(bytes: &[u8], i: usize) -> usize {
+    1 + bytes[i + 1..]
+        .iter()
+        .take_while(|&&b| (b & 0xc0) == 0x80)
+        .count()
+}
+
+#[inline(never)]
+fn tokenize_class_table(s: &str, tokens: &mut Vec<Token>) {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match CLASSES[bytes[i] as usize] {
+            SPACE => {
+                i += 1;
+                continue;
+            }
+            IDENT_START => {
+                i += 1;
+                while i < bytes.len() && matches!(CLASSES[bytes[i] as usize], IDENT_START | DIGIT) {
+                    i += 1;
+                }
+                TokenKind::Ident
+            }
+            DIGIT => {
+                i += 1;
+                while i < bytes.len() && CLASSES[bytes[i] as usize] == DIGIT {
+                    i += 1;
+                }
+                TokenKind::Number
+            }
+            PUNCT => {
+                i += 1;
+                TokenKind::Punct
+            }
+            _ => {
+                i += char_len(bytes, i);
+                TokenKind::Other
+            }
+        };
+        push_token(tokens, kind, start, i);
+    }
+}
+
+type V = u8x32;
+type M = Mask<i8, 32>;
+
+#[inline]
+fn is_digit_simd(chunk: V) -> M {
+    (chunk - V::splat(b'0')).simd_lt(V::splat(10))
+}
+
+#[inline]
+fn is_ident_simd(chunk: V) -> M {
+    let is_alpha = ((chunk | V::splat(0x20)) - V::splat(b'a')).simd_lt(V::splat(26));
+    is_alpha | is_digit_simd(chunk) | chunk.simd_eq(V::splat(b'_'))
+}
+
+#[inline]
+fn is_space_simd(chunk: V) -> M {
+    chunk.simd_eq(V::splat(b' '))
+        | chunk.simd_eq(V::splat(b'\t'))
+        | chunk.simd_eq(V::splat(b'\n'))
+        | chunk.simd_eq(V::splat(b'\r'))
+}
+
+/// End of the run of bytes starting at `i` for which `classify` is true,
+/// 32 bytes at a time while they are available, then byte by byte.
+#[inline]
+fn run_end(bytes: &[u8], mut i: usize, classify: fn(V) -> M, scalar: fn(u8) -> bool) -> usize {
+    while i + V::LANES <= bytes.len() {
+        let run = (!classify(V::from_slice(&bytes[i..])).to_bitmask()).trailing_zeros() as usize;
+        i += run;
+        if run < V::LANES {
+            return i;
+        }
+    }
+    while i < bytes.len() && scalar(bytes[i]) {
+        i += 1;
+    }
+    i
+}
+
+/// Dispatch on the class table, scan identifier, number and whitespace runs with SIMD masks.
+#[inline(never)]
+fn tokenize_simd(s: &str, tokens: &mut Vec<Token>) {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let kind = match CLASSES[bytes[i] as usize] {
+            SPACE => {
+                i = run_end(bytes, i + 1, is_space_simd, |b| {
+                    CLASSES[b as usize] == SPACE
+                });
+                continue;
+            }
+            IDENT_START => {
+                i = run_end(bytes, i + 1, is_ident_simd, |b| {
+                    matches!(CLASSES[b as usize], IDENT_START | DIGIT)
+                });
+                TokenKind::Ident
+            }
+            DIGIT => {
+                i = run_end(bytes, i + 1, is_digit_simd, |b| b.is_ascii_digit());
+                TokenKind::Number
+            }
+            PUNCT => {
+                i += 1;
+                TokenKind::Punct
+            }
+            _ => {
+                i += char_len(bytes, i);
+                TokenKind::Other
+            }
+        };
+        push_token(tokens, kind, start, i);
+    }
+}
+
+type Tokenize = fn(&str, &mut Vec<Token>);
+
+const IMPLS: &[(&str, Tokenize)] = &[
+    ("char_match", tokenize_char_match),
+    ("class_table", tokenize_class_table),
+    ("simd", tokenize_simd),
+];
+
+const PUNCTUATION: &[u8] = b"(){}[];,.=+-*/<>&|!:";
+
+/// Lines of indented identifiers, numbers and punctuation.
+fn gen_source(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    let mut s = String::with_capacity(len + 100);
+    while s.len() < len {
+        s.push_str(&" ".repeat(4 * rng.gen_range(0..=3)));
+        for _ in 0..rng.gen_range(1..=10) {
+            match rng.gen_range(0..10) {
+                0..=4 => {
+                    let mut ident = random_string(rng.gen_range(1..=12));
+                    if rng.gen_range(0..4) == 0 {
+                        ident.push('_');
+                        ident.push_str(&random_string(rng.gen_range(1..=6)));
+                    }
+                    if rng.gen_range(0..4) == 0 {
+                        ident[..1].make_ascii_uppercase();
+                    }
+                    s.push_str(&ident);
+                }
+                5 => s.push_str(&rng.gen_range(0..1_000_000).to_string()),
+                _ => s.push(PUNCTUATION[rng.gen_range(0..PUNCTUATION.len())] as char),
+            }
+            if rng.gen() {
+                s.push(' ');
+            }
+        }
+        s.push('\n');
+    }
+    s
+}
+
+fn tokenize_to_vec(f: Tokenize, s: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    f(s, &mut tokens);
+    tokens
+}
+
+fn test() {
+    let mut inputs = mixed_strings(10_000, 100, gen_source);
+    // Runs longer than one SIMD chunk.
+    inputs.push(format!(
+        "{} {}\t\t{}",
+        "a".repeat(100),
+        "9".repeat(70),
+        " ".repeat(65)
+    ));
+    differential_test(
+        &inputs,
+        |s| tokenize_to_vec(tokenize_char_match, s),
+        IMPLS,
+        |&f, s| tokenize_to_vec(f, s),
+    );
+}
+
+fn main() {
+    test();
+
+    let source = &gen_source(1 << 20);
+    let n_tokens = tokenize_to_vec(tokenize_char_match, source).len();
+    println!("{} tokens, {} bytes", n_tokens, source.len());
+    let tokens = &RefCell::new(Vec::with_capacity(n_tokens));
+    let benchmarks: Vec<Benchmark> = IMPLS
+        .iter()
+        .map(|&(name, f)| {
+            Benchmark::new(name, move || {
+                let mut tokens = tokens.borrow_mut();
+                tokens.clear();
+                f(source, &mut tokens);
+                hint::black_box(tokens.len())
+            })
+        })
+        .collect();
+    let seconds = rust_str_bench::benchmark(1, &benchmarks);
+    rust_str_bench::print_throughput(
+        &benchmarks,
+        &seconds,
+        &[("tokens", n_tokens as f64), ("bytes", source.len() as f64)],
+    );
+}
@@ -0,0 +1,406 @@
+#![feature(portable_simd)]
+
+use std::hint;
+use std::simd::u8x16;
+use std::simd::u8x32;
+use std::simd::SimdPartialEq;
+use std::simd::ToBitMask;
+
+use rand::Rng;
+use rust_str_bench::differential::differential_test;
+use rust_str_bench::random_strings::random_script_string;
+use rust_str_bench::random_strings::random_utf8_string;
+use rust_str_bench::random_strings::Script;
+use rust_str_bench::Benchmark;
+
+// DFA states.
+const ACCEPT: u8 = 0;
+const CONT_1: u8 = 1;
+const CONT_2: u8 = 2;
+const CONT_3: u8 = 3;
+/// After `E0`, next byte must be `A0..=BF` to reject overlong encodings.
+const AFTER_E0: u8 = 4;
+/// After `ED`, next byte must be `80..=9F` to reject surrogates.
+const AFTER_ED: u8 = 5;
+/// After `F0`, next byte must be `90..=BF` to reject overlong encodings.
+const AFTER_F0: u8 = 6;
+/// After `F4`, next byte must be `80..=8F` to reject code points above `10FFFF`.
+const AFTER_F4: u8 = 7;
+const REJECT: u8 = 8;
+
+const N_STATES: usize = 9;
+const N_CLASSES: usize = 12;
+
+const fn byte_class(b: u8) -> u8 {
+    match b {
+        0x00..=0x7f => 0,
+        0x80..=0x8f => 1,
+        0x90..=0x9f => 2,
+        0xa0..=0xbf => 3,
+        0xc2..=0xdf => 5,
+        0xe0 => 6,
+        0xe1..=0xec | 0xee..=0xef => 7,
+        0xed => 8,
+        0xf0 => 9,
+        0xf1..=0xf3 => 10,
+        0xf4 => 11,
+        // `C0`, `C1` and `F5..=FF` never appear in UTF-8.
+        _ => 4,
+    }
+}
+
+const fn transition(state: u8, class: u8) -> u8 {
+    match (state, class) {
+        (ACCEPT, 0) => ACCEPT,
+        (ACCEPT, 5) => CONT_1,
+        (ACCEPT, 6) => AFTER_E0,
+        (ACCEPT, 7) => CONT_2,
+        (ACCEPT, 8) => AFTER_ED,
+        (ACCEPT, 9) => AFTER_F0,
+        (ACCEPT, 10) => CONT_3,
+        (ACCEPT, 11) => AFTER_F4,
+        (CONT_1, 1..=3) => ACCEPT,
+        (CONT_2, 1..=3) => CONT_1,
+        (CONT_3, 1..=3) => CONT_2,
+        (AFTER_E0, 3) => CONT_1,
+        (AFTER_ED, 1..=2) => CONT_1,
+        (AFTER_F0, 2..=3) => CONT_2,
+        (AFTER_F4, 1) => CONT_2,
+        _ => REJECT,
+    }
+}
+
+const BYTE_CLASSES: [u8; 256] = {
+    let mut classes = [0; 256];
+    let mut b = 0;
+    while b < 256 {
+        classes[b] = byte_class(b as u8);
+        b += 1;
+    }
+    classes
+};
+
+/// Indexed by `state * N_CLASSES + class`.
+const TRANSITIONS: [u8; N_STATES * N_CLASSES] = {
+    let mut transitions = [0; N_STATES * N_CLASSES];
+    let mut i = 0;
+    while i < transitions.len() {
+        transitions[i] = transition((i / N_CLASSES) as u8, (i % N_CLASSES) as u8);
+        i += 1;
+    }
+    transitions
+};
+
+#[inline(never)]
+fn validate_from_utf8(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok()
+}
+
+/// Table-driven DFA, `REJECT` is absorbing so there is no early exit.
+#[inline(never)]
+fn validate_dfa(bytes: &[u8]) -> bool {
+    let mut state = ACCEPT;
+    for &b in bytes {
+        let class = BYTE_CLASSES[b as usize];
+        state = TRANSITIONS[state as usize * N_CLASSES + class as usize];
+    }
+    state == ACCEPT
+}
+
+// Error bits of the lookup validator, set for pairs of bytes
+// which are invalid when they appear next to each other.
+/// `11______ 0_______` or `11______ 11______`.
+const TOO_SHORT: u8 = 1 << 0;
+/// `0_______ 10______`.
+const TOO_LONG: u8 = 1 << 1;
+/// `11100000 100_____`.
+const OVERLONG_3: u8 = 1 << 2;
+/// `11110100 1001____`, `11110100 101_____`, `11110101..11111111 1001____`...
+const TOO_LARGE: u8 = 1 << 3;
+/// `11101101 101_____`.
+const SURROGATE: u8 = 1 << 4;
+/// `1100000_ 10______`.
+const OVERLONG_2: u8 = 1 << 5;
+/// `11110101..11111111 1000____`.
+const TOO_LARGE_1000: u8 = 1 << 6;
+/// `11110000 1000____`.
+const OVERLONG_4: u8 = 1 << 6;
+/// `10______ 10______`, valid only as second and later continuation bytes.
+const TWO_CONTS: u8 = 1 << 7;
+const CARRY: u8 = TOO_SHORT | TOO_LONG | TWO_CONTS;
+
+/// Error bits by high nibble of the first byte of a pair.
+const BYTE_1_HIGH: [u8; 16] = [
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TOO_LONG,
+    TWO_CONTS,
+    TWO_CONTS,
+    TWO_CONTS,
+    TWO_CONTS,
+    TOO_SHORT | OVERLONG_2,
+    TOO_SHORT,
+    TOO_SHORT | OVERLONG_3 | SURROGATE,
+    TOO_SHORT | TOO_LARGE | TOO_LARGE_1000 | OVERLONG_4,
+];
+
+/// Error bits by low nibble of the first byte of a pair.
+const BYTE_1_LOW: [u8; 16] = [
+    CARRY | OVERLONG_3 | OVERLONG_2 | OVERLONG_4,
+    CARRY | OVERLONG_2,
+    CARRY,
+    CARRY,
+    CARRY | TOO_LARGE,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000 | SURROGATE,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+    CARRY | TOO_LARGE | TOO_LARGE_1000,
+];
+
+/// Error bits by high nibble of the second byte of a pair.
+const BYTE_2_HIGH: [u8; 16] = [
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_LONG | OVERLONG_2 | TWO_CONTS | OVERLONG_3 | TOO_LARGE_1000 | OVERLONG_4,
+    TOO_LONG | OVERLONG_2 | TWO_CONTS | OVERLONG_3 | TOO_LARGE,
+    TOO_LONG | OVERLONG_2 | TWO_CONTS | SURROGATE | TOO_LARGE,
+    TOO_LONG | OVERLONG_2 | TWO_CONTS | SURROGATE | TOO_LARGE,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+    TOO_SHORT,
+];
+
+/// 16 bytes starting at `start`, bytes outside of `bytes` are zero.
+#[inline]
+fn load_16(bytes: &[u8], start: isize) -> u8x16 {
+    if start >= 0 && start as usize + 16 <= bytes.len() {
+        return u8x16::from_slice(&bytes[start as usize..]);
+    }
+    let mut buf = [0; 16];
+    for (i, b) in buf.iter_mut().enumerate() {
+        let j = start + i as isize;
+        if j >= 0 && (j as usize) < bytes.len() {
+            *b = bytes[j as usize];
+        }
+    }
+    u8x16::from_array(buf)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+#[inline]
+unsafe fn shuffle(table: u8x16, indices: u8x16) -> u8x16 {
+    std::arch::x86_64::_mm_shuffle_epi8(table.into(), indices.into()).into()
+}
+
+/// Keiser–Lemire lookup validator: classify each pair of adjacent bytes
+/// by three nibble lookups, then check that the third and fourth bytes
+/// of multibyte sequences are continuation bytes.
+///
+/// Bytes past the end are zero, so a truncated sequence at the end
+/// is reported as `TOO_SHORT` by the last chunk.
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+unsafe fn validate_lookup_ssse3(bytes: &[u8]) -> bool {
+    type V = u8x16;
+    let byte_1_high = V::from_array(BYTE_1_HIGH);
+    let byte_1_low = V::from_array(BYTE_1_LOW);
+    let byte_2_high = V::from_array(BYTE_2_HIGH);
+    let mut error = V::splat(0);
+    for i in (0..=bytes.len()).step_by(V::LANES) {
+        let i = i as isize;
+        let input = load_16(bytes, i);
+        let prev3 = load_16(bytes, i - 3);
+        // No multibyte sequence starts in or continues into this chunk.
+        if ((input | prev3) & V::splat(0x80)) == V::splat(0) {
+            continue;
+        }
+        let prev1 = load_16(bytes, i - 1);
+        let prev2 = load_16(bytes, i - 2);
+        let special_cases = shuffle(byte_1_high, prev1 >> V::splat(4))
+            & shuffle(byte_1_low, prev1 & V::splat(0xf))
+            & shuffle(byte_2_high, input >> V::splat(4));
+        // High bit is set if `prev2` is `111_____` or `prev3` is `1111____`.
+        let must_be_continuation = (prev2 & (prev2 << V::splat(1)) & (prev2 << V::splat(2)))
+            | (prev3 & (prev3 << V::splat(1)) & (prev3 << V::splat(2)) & (prev3 << V::splat(3)));
+        error |= (must_be_continuation & V::splat(0x80)) ^ special_cases;
+    }
+    error == V::splat(0)
+}
+
+#[inline(never)]
+fn validate_lookup(bytes: &[u8]) -> bool {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("ssse3") {
+        return unsafe { validate_lookup_ssse3(bytes) };
+    }
+    validate_dfa(bytes)
+}
+
+#[inline(never)]
+fn count_chars(s: &str) -> usize {
+    s.chars().count()
+}
+
+#[inline(never)]
+fn count_non_continuation_bytes(s: &str) -> usize {
+    s.bytes().filter(|&b| (b & 0xc0) != 0x80).count()
+}
+
+/// Popcount of 32-byte masks of bytes which are not `10______`.
+#[inline(never)]
+fn count_non_continuation_simd(s: &str) -> usize {
+    type V = u8x32;
+    let mut chunks = s.as_bytes().chunks_exact(V::LANES);
+    let mut count = 0;
+    for chunk in &mut chunks {
+        let starts = (V::from_slice(chunk) & V::splat(0xc0)).simd_ne(V::splat(0x80));
+        count += starts.to_bitmask().count_ones() as usize;
+    }
+    count
+        + chunks
+            .remainder()
+            .iter()
+            .filter(|&&b| (b & 0xc0) != 0x80)
+            .count()
+}
+
+type Validate = fn(&[u8]) -> bool;
+type CountChars = fn(&str) -> usize;
+
+const VALIDATE_IMPLS: &[(&str, Validate)] = &[
+    ("from_utf8", validate_from_utf8),
+    ("dfa", validate_dfa),
+    ("lookup", validate_lookup),
+];
+
+const COUNT_IMPLS: &[(&str, CountChars)] = &[
+    ("chars().count()", count_chars),
+    ("non_continuation_bytes", count_non_continuation_bytes),
+    ("non_continuation_simd", count_non_continuation_simd),
+];
+
+/// Bytes around boundaries of UTF-8 byte classes.
+const INTERESTING_BYTES: &[u8] = &[
+    0x00, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf, 0xc0, 0xc1, 0xc2, 0xdf, 0xe0, 0xe1, 0xec, 0xed,
+    0xee, 0xef, 0xf0, 0xf1, 0xf3, 0xf4, 0xf5, 0xff,
+];
+
+fn gen_invalid_inputs() -> Vec<Vec<u8>> {
+    let mut rng = rand::thread_rng();
+    let mut inputs = Vec::new();
+    // All sequences of up to 3 interesting bytes, alone and across a chunk boundary.
+    let mut sequences = vec![Vec::new()];
+    for _ in 0..3 {
+        let mut next = Vec::new();
+        for s in &sequences {
+            for &b in INTERESTING_BYTES {
+                let mut s = s.clone();
+                s.push(b);
+                next.push(s);
+            }
+        }
+        inputs.extend(next.iter().cloned());
+        sequences = next;
+    }
+    for s in &sequences {
+        let mut input = vec![b'a'; 14];
+        input.extend_from_slice(s);
+        input.extend_from_slice(b"bcd");
+        inputs.push(input);
+    }
+    // Valid strings with a mutated, inserted or truncated byte.
+    for _ in 0..100_000 {
+        let mut input = random_utf8_string(rng.gen_range(0..=40)).into_bytes();
+        let b = INTERESTING_BYTES[rng.gen_range(0..INTERESTING_BYTES.len())];
+        match rng.gen_range(0..4) {
+            0 if !input.is_empty() => {
+                let i = rng.gen_range(0..input.len());
+                input[i] = b;
+            }
+            1 => input.insert(rng.gen_range(0..=input.len()), b),
+            2 => input.truncate(rng.gen_range(0..=input.len())),
+            _ => {}
+        }
+        inputs.push(input);
+    }
+    inputs
+}
+
+/// Strings of 1000 chars, repeated scripts are proportionally more frequent.
+fn gen_strings(scripts: &[Script]) -> Vec<String> {
+    (0..100)
+        .map(|_| random_script_string(scripts, 1000))
+        .collect()
+}
+
+fn test() {
+    let inputs = gen_invalid_inputs();
+    differential_test(
+        &inputs,
+        |b| std::str::from_utf8(b).is_ok(),
+        VALIDATE_IMPLS,
+        |f, b| f(b),
+    );
+
+    let mut rng = rand::thread_rng();
+    let strings: Vec<String> = (0..10_000)
+        .map(|_| random_utf8_string(rng.gen_range(0..=100)))
+        .collect();
+    differential_test(&strings, |s| s.chars().count(), COUNT_IMPLS, |f, s| f(s));
+}
+
+fn main() {
+    test();
+
+    let mut mostly_ascii = vec![Script::Ascii; 19];
+    mostly_ascii.push(Script::Cyrillic);
+    let corpora = [
+        ("ascii", gen_strings(&[Script::Ascii])),
+        ("mostly_ascii", gen_strings(&mostly_ascii)),
+        ("cjk", gen_strings(&[Script::Cjk])),
+    ];
+    let mut benchmarks = Vec::new();
+    for (corpus, strings) in &corpora {
+        for &(name, f) in VALIDATE_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("{} validate {}", corpus, name),
+                move || {
+                    for s in strings {
+                        hint::black_box(f(s.as_bytes()));
+                    }
+                },
+            ));
+        }
+        for &(name, f) in COUNT_IMPLS {
+            benchmarks.push(Benchmark::new(
+                &format!("{} count {}", corpus, name),
+                move || {
+                    for s in strings {
+                        hint::black_box(f(s));
+                    }
+                },
+            ));
+        }
+    }
+    rust_str_bench::benchmark(100, &benchmarks);
+}
@@ -0,0 +1,142 @@
+use std::hint;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Barrier;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::affinity;
+use crate::affinity::Placement;
+use crate::aligned_writer::AlignedWriter;
+
+/// Number of operations executed between checks of the stop flag.
+const BATCH: u64 = 1000;
+
+pub struct ConcurrentBenchmark<'a> {
+    name: String,
+    run: Box<dyn Fn(usize) + Sync + 'a>,
+}
+
+impl<'a> ConcurrentBenchmark<'a> {
+    /// `f` performs one operation, it is called with the thread index.
+    pub fn new<R>(name: &str, f: impl Fn(usize) -> R + Sync + 'a) -> ConcurrentBenchmark<'a> {
+        ConcurrentBenchmark {
+            name: name.to_owned(),
+            run: Box::new(move |thread| {
+                for _ in 0..BATCH {
+                    hint::black_box(f(thread));
+                }
+            }),
+        }
+    }
+}
+
+/// Operations performed by each thread during one run.
+struct Run {
+    ops: Vec<u64>,
+    elapsed: Duration,
+}
+
+impl Run {
+    fn total_ops_per_second(&self) -> f64 {
+        self.ops.iter().sum::<u64>() as f64 / self.elapsed.as_secs_f64()
+    }
+
+    fn thread_ops_per_second(&self) -> impl Iterator<Item = f64> + '_ {
+        self.ops
+            .iter()
+            .map(|&ops| ops as f64 / self.elapsed.as_secs_f64())
+    }
+}
+
+fn run_once(
+    n_threads: usize,
+    duration: Duration,
+    cpus: &[usize],
+    benchmark: &ConcurrentBenchmark,
+) -> Run {
+    let stop = AtomicBool::new(false);
+    let barrier = Barrier::new(n_threads + 1);
+    thread::scope(|s| {
+        let threads: Vec<_> = (0..n_threads)
+            .map(|i| {
+                let stop = &stop;
+                let barrier = &barrier;
+                s.spawn(move || {
+                    if let Some(cpu) = affinity::cpu_for_thread(cpus, i) {
+                        affinity::pin_current_thread(cpu);
+                    }
+                    barrier.wait();
+                    let mut ops = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        (benchmark.run)(i);
+                        ops += BATCH;
+                    }
+                    ops
+                })
+            })
+            .collect();
+        barrier.wait();
+        let start = Instant::now();
+        thread::sleep(duration);
+        stop.store(true, Ordering::Relaxed);
+        // End at the stop, joining also waits for each thread to finish its current batch.
+        let elapsed = start.elapsed();
+        let ops = threads.into_iter().map(|t| t.join().unwrap()).collect();
+        Run { ops, elapsed }
+    })
+}
+
+/// Run each benchmark on `n_threads` threads for `duration`,
+/// threads are pinned to CPUs according to `placement`.
+///
+/// Returns total throughput (operations per second across all threads) for each benchmark.
+pub fn concurrent_benchmark(
+    n_threads: usize,
+    duration: Duration,
+    placement: Placement,
+    benchmarks: &[ConcurrentBenchmark],
+) -> Vec<f64> {
+    let cpus = affinity::cpu_order(placement);
+    affinity::check_oversubscription(&cpus, n_threads);
+    println!(
+        "threads: {}, duration: {:?}, placement: {:?}, cpus: {:?}",
+        n_threads,
+        duration,
+        placement,
+        (0..n_threads)
+            .filter_map(|i| affinity::cpu_for_thread(&cpus, i))
+            .collect::<Vec<_>>()
+    );
+    let runs: Vec<Run> = benchmarks
+        .iter()
+        .map(|b| {
+            let run = run_once(n_threads, duration, &cpus, b);
+            println!("{}: {:.3}Mops/s", b.name, run.total_ops_per_second() / 1e6);
+            run
+        })
+        .collect();
+
+    let mut w = AlignedWriter::new(benchmarks.len());
+    w.write_n_l(benchmarks.iter().map(|b| format!("{}:", b.name.as_str())));
+    w.write(" total=");
+    w.write_n_r(
+        runs.iter()
+            .map(|r| format!("{:.3}Mops/s", r.total_ops_per_second() / 1e6)),
+    );
+    w.write(" thread_min=");
+    w.write_n_r(runs.iter().map(|r| {
+        let min = r.thread_ops_per_second().fold(f64::INFINITY, f64::min);
+        format!("{:.3}Mops/s", min / 1e6)
+    }));
+    w.write(" thread_max=");
+    w.write_n_r(runs.iter().map(|r| {
+        let max = r.thread_ops_per_second().fold(0.0, f64::max);
+        format!("{:.3}Mops/s", max / 1e6)
+    }));
+    println!("Summary ({:?}):", placement);
+    w.print();
+
+    runs.iter().map(|r| r.total_ops_per_second()).collect()
+}
@@ -0,0 +1,31 @@
+use std::fmt::Debug;
+
+/// Check that each implementation returns the same result as `reference` on each input,
+/// panic with the input and both results on the first mismatch.
+///
+/// `call` adapts an entry of `implementations` (usually a plain `fn` from an `IMPLS`
+/// registry) to the input type.
+pub fn differential_test<I: Debug, R: PartialEq + Debug, F>(
+    inputs: &[I],
+    reference: impl Fn(&I) -> R,
+    implementations: &[(&str, F)],
+    call: impl Fn(&F, &I) -> R,
+) {
+    for input in inputs {
+        let expected = reference(input);
+        for (name, f) in implementations {
+            let actual = call(f, input);
+            if actual != expected {
+                panic!(
+                    "{}: input: {:?}, expected: {:?}, actual: {:?}",
+                    name, input, expected, actual
+                );
+            }
+        }
+    }
+    println!(
+        "Tested {} implementations on {} inputs",
+        implementations.len(),
+        inputs.len()
+    );
+}
@@ -0,0 +1,166 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::hash::BuildHasherDefault;
+use std::hash::Hasher;
+
+use hashbrown::raw::RawTable;
+
+/// Maps strings to `u32` ids, equal strings get equal ids.
+pub trait Interner: Default {
+    /// Id of `s`, inserting `s` if it is not interned yet.
+    fn intern(&mut self, s: &str) -> u32;
+
+    /// String interned as `id`.
+    fn resolve(&self, id: u32) -> &str;
+}
+
+/// Default hasher of the arena interners.
+pub type Xxh3BuildHasher = BuildHasherDefault<xxhash_rust::xxh3::Xxh3>;
+
+/// Hash of the bytes of `s`, without the terminator `Hash for str` adds.
+#[inline]
+fn hash<S: BuildHasher>(hash_builder: &S, s: &str) -> u64 {
+    let mut hasher = hash_builder.build_hasher();
+    hasher.write(s.as_bytes());
+    hasher.finish()
+}
+
+/// Reference implementation: owned strings in a `HashMap` and in a `Vec` for `resolve`.
+#[derive(Default)]
+pub struct HashMapInterner<S = RandomState> {
+    ids: HashMap<String, u32, S>,
+    strings: Vec<String>,
+}
+
+impl<S: BuildHasher + Default> Interner for HashMapInterner<S> {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&id) = self.ids.get(s) {
+            return id;
+        }
+        let id = u32::try_from(self.strings.len()).expect("too many strings for u32 ids");
+        self.ids.insert(s.to_owned(), id);
+        self.strings.push(s.to_owned());
+        id
+    }
+
+    fn resolve(&self, id: u32) -> &str {
+        &self.strings[id as usize]
+    }
+}
+
+/// Strings are concatenated in one arena, table stores ids
+/// and is rehashed from precomputed hashes.
+#[derive(Default)]
+pub struct RawTableInterner<S = Xxh3BuildHasher> {
+    arena: String,
+    /// Start and end of each string in `arena`.
+    spans: Vec<(u32, u32)>,
+    hashes: Vec<u64>,
+    table: RawTable<u32>,
+    hash_builder: S,
+}
+
+impl<S: BuildHasher + Default> Interner for RawTableInterner<S> {
+    fn intern(&mut self, s: &str) -> u32 {
+        let hash = hash(&self.hash_builder, s);
+        let (arena, spans) = (&self.arena, &self.spans);
+        let found = self.table.get(hash, |&id| {
+            let (start, end) = spans[id as usize];
+            &arena[start as usize..end as usize] == s
+        });
+        if let Some(&id) = found {
+            return id;
+        }
+        let id = u32::try_from(self.spans.len()).expect("too many strings for u32 ids");
+        let start = u32::try_from(self.arena.len()).expect("arena longer than u32::MAX");
+        let end = u32::try_from(self.arena.len() + s.len()).expect("arena longer than u32::MAX");
+        self.arena.push_str(s);
+        self.spans.push((start, end));
+        self.hashes.push(hash);
+        let hashes = &self.hashes;
+        self.table.insert(hash, id, |&id| hashes[id as usize]);
+        id
+    }
+
+    fn resolve(&self, id: u32) -> &str {
+        let (start, end) = self.spans[id as usize];
+        &self.arena[start as usize..end as usize]
+    }
+}
+
+/// Strings up to this length are stored in per-length buckets.
+pub const MAX_BUCKETED_LEN: usize = 32;
+
+/// Strings of one length stored back to back, so index determines the position.
+#[derive(Default)]
+struct LenBucket {
+    arena: String,
+    hashes: Vec<u64>,
+    table: RawTable<u32>,
+}
+
+/// Short strings are compared only with strings of the same length,
+/// and resolved without a span lookup.
+///
+/// Id is `index << 6 | len` for short strings, `index << 6 | 63` for long strings.
+pub struct LenBucketedInterner<S = Xxh3BuildHasher> {
+    buckets: Vec<LenBucket>,
+    /// Long strings, its hasher is used for buckets too.
+    long: RawTableInterner<S>,
+}
+
+const LONG_TAG: u32 = 63;
+
+/// Id of string `index` in a bucket or in the long strings interner.
+fn tagged_id(index: u32, tag: u32) -> u32 {
+    assert!(index < 1 << 26, "too many strings for 26-bit index");
+    index << 6 | tag
+}
+
+impl<S: Default> Default for LenBucketedInterner<S> {
+    fn default() -> LenBucketedInterner<S> {
+        LenBucketedInterner {
+            buckets: (0..=MAX_BUCKETED_LEN)
+                .map(|_| LenBucket::default())
+                .collect(),
+            long: RawTableInterner::default(),
+        }
+    }
+}
+
+impl<S: BuildHasher + Default> Interner for LenBucketedInterner<S> {
+    fn intern(&mut self, s: &str) -> u32 {
+        let len = s.len();
+        let bucket = match self.buckets.get_mut(len) {
+            Some(bucket) => bucket,
+            None => return tagged_id(self.long.intern(s), LONG_TAG),
+        };
+        let hash = hash(&self.long.hash_builder, s);
+        let arena = &bucket.arena;
+        let found = bucket.table.get(hash, |&index| {
+            let start = index as usize * len;
+            &arena[start..start + len] == s
+        });
+        if let Some(&index) = found {
+            return tagged_id(index, len as u32);
+        }
+        let index = u32::try_from(bucket.hashes.len()).expect("too many strings for u32 ids");
+        bucket.arena.push_str(s);
+        bucket.hashes.push(hash);
+        let hashes = &bucket.hashes;
+        bucket
+            .table
+            .insert(hash, index, |&index| hashes[index as usize]);
+        tagged_id(index, len as u32)
+    }
+
+    fn resolve(&self, id: u32) -> &str {
+        let (index, len) = ((id >> 6) as usize, id & 63);
+        if len == LONG_TAG {
+            return self.long.resolve(index as u32);
+        }
+        let len = len as usize;
+        &self.buckets[len].arena[index * len..(index + 1) * len]
+    }
+}
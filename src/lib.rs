@@ -1,5 +1,14 @@
-mod aligned_writer;
+pub mod affinity;
+pub mod aligned_writer;
+pub mod alloc_counter;
+pub mod concurrent;
+pub mod differential;
+pub mod histogram;
+pub mod interner;
+pub mod lock_bench;
+pub mod model_check;
 pub mod random_strings;
+pub mod small_string;
 
 use std::hint;
 use std::time::Instant;
@@ -82,6 +91,9 @@ impl Stats {
 }
 
 pub fn benchmark(iterations_in_benchmark: usize, benchmarks: &[Benchmark]) -> Vec<f64> {
+    if let Some(cpu) = affinity::pin_current_thread_from_env() {
+        println!("Pinned to CPU {}", cpu);
+    }
     println!("Calculating batch size...");
     let batch_size = batch_size(benchmarks);
     println!("batch_size: {}", batch_size);
@@ -134,3 +146,18 @@ pub fn benchmark(iterations_in_benchmark: usize, benchmarks: &[Benchmark]) -> Ve
         }
     }
 }
+
+/// Print throughput of each benchmark given seconds per run returned by `benchmark`.
+///
+/// `units` are pairs of unit name and number of units processed by one run.
+pub fn print_throughput(benchmarks: &[Benchmark], seconds: &[f64], units: &[(&str, f64)]) {
+    assert_eq!(benchmarks.len(), seconds.len());
+    let mut w = AlignedWriter::new(benchmarks.len());
+    w.write_n_l(benchmarks.iter().map(|b| format!("{}:", b.name.as_str())));
+    for &(unit, count) in units {
+        w.write(format!(" {}/s=", unit));
+        w.write_n_r(seconds.iter().map(|&s| format!("{:.3}M", count / s / 1e6)));
+    }
+    println!("Throughput:");
+    w.print();
+}
@@ -0,0 +1,275 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::affinity;
+use crate::affinity::Placement;
+use crate::aligned_writer::AlignedWriter;
+use crate::histogram::Histogram;
+
+/// Time one of this many acquisitions individually.
+const SAMPLE_EVERY: u64 = 16;
+
+/// Number of empty `Instant::now` pairs timed to estimate timer overhead.
+const CALIBRATION_SAMPLES: usize = 10_000;
+
+/// Number of acquisitions between checks of the stop flag.
+const BATCH: u64 = 100_000;
+
+/// Command line options shared by lock benchmarks.
+pub struct LockBenchArgs {
+    pub n_threads: usize,
+    /// Stop after this time.
+    pub duration: Duration,
+    /// Stop each thread after this many acquisitions.
+    pub iterations: Option<u64>,
+    /// Write per-thread results to this CSV file.
+    pub csv: Option<PathBuf>,
+    pub placement: Placement,
+}
+
+impl Default for LockBenchArgs {
+    fn default() -> LockBenchArgs {
+        LockBenchArgs {
+            n_threads: 8,
+            duration: Duration::from_secs(10),
+            iterations: None,
+            csv: None,
+            placement: Placement::Unpinned,
+        }
+    }
+}
+
+impl LockBenchArgs {
+    /// Parse `--threads N --seconds N --iterations N --csv PATH --placement P`.
+    pub fn parse() -> LockBenchArgs {
+        let mut r = LockBenchArgs::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .unwrap_or_else(|| panic!("missing value for {}", arg))
+            };
+            match arg.as_str() {
+                "--threads" => r.n_threads = value().parse().unwrap(),
+                "--seconds" => r.duration = Duration::from_secs_f64(value().parse().unwrap()),
+                "--iterations" => {
+                    let iterations = value().parse().unwrap();
+                    assert!(iterations > 0, "--iterations must be positive");
+                    r.iterations = Some(iterations);
+                }
+                "--csv" => r.csv = Some(PathBuf::from(value())),
+                "--placement" => r.placement = value().parse().unwrap(),
+                _ => panic!("unknown argument: {}", arg),
+            }
+        }
+        r
+    }
+}
+
+struct ThreadResult {
+    cpu: Option<usize>,
+    acquisitions: u64,
+    elapsed: Duration,
+    histogram: Histogram,
+}
+
+impl ThreadResult {
+    fn avg_ns(&self) -> f64 {
+        self.elapsed.as_nanos() as f64 / self.acquisitions as f64
+    }
+}
+
+/// Minimum time between two back-to-back `Instant::now` calls.
+///
+/// Sampled latencies include one such pair, so this is subtracted from each sample.
+/// Minimum rather than average, so fast acquisitions are not clamped to zero,
+/// which leaves samples biased upwards by the timer jitter.
+fn timer_overhead_ns() -> u64 {
+    (0..CALIBRATION_SAMPLES)
+        .map(|_| {
+            let start = Instant::now();
+            start.elapsed().as_nanos() as u64
+        })
+        .min()
+        .unwrap()
+}
+
+fn run_thread(
+    i: usize,
+    cpu: Option<usize>,
+    timer_overhead_ns: u64,
+    args: &LockBenchArgs,
+    stop: &AtomicBool,
+    acquire_release: &(impl Fn() + Sync),
+) -> ThreadResult {
+    if let Some(cpu) = cpu {
+        affinity::pin_current_thread(cpu);
+    }
+    let thread_start = Instant::now();
+    let mut acquisitions = 0;
+    let mut histogram = Histogram::default();
+    let remaining = |acquisitions: u64| match args.iterations {
+        Some(iterations) => iterations - acquisitions,
+        None => u64::MAX,
+    };
+    while !stop.load(Ordering::Relaxed) && remaining(acquisitions) != 0 {
+        let start = Instant::now();
+        let mut total = 0;
+        let mut interval_histogram = Histogram::default();
+        while start.elapsed() < Duration::from_secs(1)
+            && !stop.load(Ordering::Relaxed)
+            && remaining(acquisitions + total) != 0
+        {
+            let batch = BATCH.min(remaining(acquisitions + total));
+            for j in 0..batch {
+                if j % SAMPLE_EVERY == 0 {
+                    let sample_start = Instant::now();
+                    acquire_release();
+                    let ns = sample_start.elapsed().as_nanos() as u64;
+                    interval_histogram.record(ns.saturating_sub(timer_overhead_ns));
+                } else {
+                    acquire_release();
+                }
+            }
+            total += batch;
+        }
+        if total == 0 {
+            break;
+        }
+        let elapsed_ns = start.elapsed().as_nanos() as u64;
+        println!(
+            "Thread {}: {}ns {}",
+            i,
+            elapsed_ns / total,
+            interval_histogram.summary()
+        );
+        acquisitions += total;
+        histogram.merge(&interval_histogram);
+    }
+    ThreadResult {
+        cpu,
+        acquisitions,
+        elapsed: thread_start.elapsed(),
+        histogram,
+    }
+}
+
+fn format_cpu(cpu: Option<usize>) -> String {
+    match cpu {
+        Some(cpu) => cpu.to_string(),
+        None => "-".to_owned(),
+    }
+}
+
+fn print_summary(results: &[ThreadResult], total: &ThreadResult) {
+    let rows = results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (format!("Thread {}:", i), r))
+        .chain([("Total:".to_owned(), total)])
+        .collect::<Vec<_>>();
+    let mut w = AlignedWriter::new(rows.len());
+    w.write_n_l(rows.iter().map(|(name, _)| name));
+    w.write(" cpu=");
+    w.write_n_l(rows.iter().map(|(_, r)| format_cpu(r.cpu)));
+    w.write(" acquisitions=");
+    w.write_n_r(rows.iter().map(|(_, r)| r.acquisitions));
+    w.write(" avg=");
+    w.write_n_r(rows.iter().map(|(_, r)| format!("{:.3}ns", r.avg_ns())));
+    w.write(" ");
+    w.write_n_l(rows.iter().map(|(_, r)| r.histogram.summary()));
+    println!("Summary:");
+    w.print();
+}
+
+fn write_csv(path: &Path, results: &[ThreadResult], total: &ThreadResult) {
+    let mut csv = String::new();
+    writeln!(
+        csv,
+        "thread,cpu,acquisitions,avg_ns,p50_ns,p99_ns,p99_9_ns,max_ns"
+    )
+    .unwrap();
+    let rows = results
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (i.to_string(), r))
+        .chain([("total".to_owned(), total)]);
+    for (thread, r) in rows {
+        writeln!(
+            csv,
+            "{},{},{},{:.3},{},{},{},{}",
+            thread,
+            format_cpu(r.cpu),
+            r.acquisitions,
+            r.avg_ns(),
+            r.histogram.percentile(50.0),
+            r.histogram.percentile(99.0),
+            r.histogram.percentile(99.9),
+            r.histogram.max()
+        )
+        .unwrap();
+    }
+    fs::write(path, csv).unwrap();
+    println!("Written {}", path.display());
+}
+
+/// Call `acquire_release` in a loop on each of `args.n_threads` threads,
+/// printing average time per call and sampled latency percentiles
+/// for each thread every second, and a summary when all threads stop.
+///
+/// Sampled latencies have the measured timer overhead subtracted,
+/// averages still include the timer calls of sampled acquisitions.
+pub fn lock_benchmark(args: &LockBenchArgs, acquire_release: impl Fn() + Sync) {
+    let acquire_release = &acquire_release;
+    let stop = AtomicBool::new(false);
+    let stop = &stop;
+    let cpus = affinity::cpu_order(args.placement);
+    affinity::check_oversubscription(&cpus, args.n_threads);
+    let timer_overhead_ns = timer_overhead_ns();
+    println!(
+        "threads: {}, placement: {:?}, timer overhead: {}ns",
+        args.n_threads, args.placement, timer_overhead_ns
+    );
+    let start = Instant::now();
+    let results: Vec<ThreadResult> = thread::scope(|s| {
+        let threads: Vec<_> = (0..args.n_threads)
+            .map(|i| {
+                let cpu = affinity::cpu_for_thread(&cpus, i);
+                s.spawn(move || run_thread(i, cpu, timer_overhead_ns, args, stop, acquire_release))
+            })
+            .collect();
+        while !threads.iter().all(|t| t.is_finished()) {
+            if start.elapsed() >= args.duration {
+                stop.store(true, Ordering::Relaxed);
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+
+    let mut total = ThreadResult {
+        cpu: None,
+        acquisitions: 0,
+        elapsed: Duration::ZERO,
+        histogram: Histogram::default(),
+    };
+    for r in &results {
+        total.acquisitions += r.acquisitions;
+        total.elapsed += r.elapsed;
+        total.histogram.merge(&r.histogram);
+    }
+
+    print_summary(&results, &total);
+    if let Some(csv) = &args.csv {
+        write_csv(csv, &results, &total);
+    }
+}
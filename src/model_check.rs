@@ -0,0 +1,332 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::mem;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::thread;
+
+/// `compare_exchange_weak` is allowed to fail spuriously this many times per execution.
+/// Each spurious failure multiplies the number of explored executions,
+/// and one is enough to exercise retry loops.
+const MAX_SPURIOUS_FAILURES: usize = 1;
+
+/// Subset of `AtomicUsize` API, implemented by both
+/// `std` atomic and [`ModelAtomicUsize`].
+pub trait AtomicUsizeOps: Sync {
+    fn new(value: usize) -> Self;
+    fn load(&self, order: Ordering) -> usize;
+    fn fetch_add(&self, value: usize, order: Ordering) -> usize;
+    fn fetch_sub(&self, value: usize, order: Ordering) -> usize;
+    fn compare_exchange_weak(
+        &self,
+        current: usize,
+        new: usize,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<usize, usize>;
+}
+
+impl AtomicUsizeOps for AtomicUsize {
+    #[inline]
+    fn new(value: usize) -> Self {
+        AtomicUsize::new(value)
+    }
+
+    #[inline]
+    fn load(&self, order: Ordering) -> usize {
+        AtomicUsize::load(self, order)
+    }
+
+    #[inline]
+    fn fetch_add(&self, value: usize, order: Ordering) -> usize {
+        AtomicUsize::fetch_add(self, value, order)
+    }
+
+    #[inline]
+    fn fetch_sub(&self, value: usize, order: Ordering) -> usize {
+        AtomicUsize::fetch_sub(self, value, order)
+    }
+
+    #[inline]
+    fn compare_exchange_weak(
+        &self,
+        current: usize,
+        new: usize,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<usize, usize> {
+        AtomicUsize::compare_exchange_weak(self, current, new, success, failure)
+    }
+}
+
+#[derive(Debug)]
+struct Choice {
+    chosen: usize,
+    options: usize,
+}
+
+/// Sequence of choices made during an execution,
+/// replayed and then extended depth-first by the next execution.
+#[derive(Default)]
+struct Schedule {
+    choices: Vec<Choice>,
+    pos: usize,
+}
+
+impl Schedule {
+    fn choose(&mut self, options: usize) -> usize {
+        assert!(options > 0);
+        if self.pos == self.choices.len() {
+            self.choices.push(Choice { chosen: 0, options });
+        }
+        let choice = &self.choices[self.pos];
+        assert_eq!(
+            choice.options, options,
+            "execution is not deterministic under fixed schedule"
+        );
+        self.pos += 1;
+        choice.chosen
+    }
+
+    /// Switch to the next unexplored schedule, return `false` if there are none.
+    fn advance(&mut self) -> bool {
+        self.pos = 0;
+        while let Some(choice) = self.choices.last_mut() {
+            if choice.chosen + 1 < choice.options {
+                choice.chosen += 1;
+                return true;
+            }
+            self.choices.pop();
+        }
+        false
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum ThreadState {
+    NotStarted,
+    Running,
+    /// Waiting before an atomic operation.
+    Parked,
+    Finished,
+}
+
+struct SchedulerState {
+    schedule: Schedule,
+    threads: Vec<ThreadState>,
+    spurious_failures: usize,
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+impl SchedulerState {
+    /// Called when running thread parks or finishes.
+    ///
+    /// Threads are started one by one and run until their first atomic operation,
+    /// so choices are only made between threads about to perform an operation.
+    fn switch(&mut self) {
+        if let Some(next) = self
+            .threads
+            .iter()
+            .position(|&t| t == ThreadState::NotStarted)
+        {
+            self.threads[next] = ThreadState::Running;
+            return;
+        }
+        let parked: Vec<usize> = (0..self.threads.len())
+            .filter(|&i| self.threads[i] == ThreadState::Parked)
+            .collect();
+        if !parked.is_empty() {
+            let next = parked[self.schedule.choose(parked.len())];
+            self.threads[next] = ThreadState::Running;
+        }
+    }
+}
+
+/// Only one model thread runs at a time, others are blocked in [`Scheduler::wait_turn`].
+struct Scheduler {
+    state: Mutex<SchedulerState>,
+    cond: Condvar,
+}
+
+impl Scheduler {
+    fn wait_turn<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, SchedulerState>,
+        thread: usize,
+    ) -> MutexGuard<'a, SchedulerState> {
+        while state.threads[thread] != ThreadState::Running {
+            state = self.cond.wait(state).unwrap();
+        }
+        state
+    }
+
+    fn yield_now(&self, thread: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.threads[thread] = ThreadState::Parked;
+        state.switch();
+        self.cond.notify_all();
+        drop(self.wait_turn(state, thread));
+    }
+
+    fn finish(&self, thread: usize, panic: Option<Box<dyn Any + Send>>) {
+        let mut state = self.state.lock().unwrap();
+        state.threads[thread] = ThreadState::Finished;
+        if state.panic.is_none() {
+            state.panic = panic;
+        }
+        state.switch();
+        self.cond.notify_all();
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<(Arc<Scheduler>, usize)>> = const { RefCell::new(None) };
+}
+
+/// Let scheduler switch to another model thread.
+/// No-op when called outside of model thread.
+fn yield_point() {
+    CURRENT.with(|current| {
+        if let Some((scheduler, thread)) = &*current.borrow() {
+            scheduler.yield_now(*thread);
+        }
+    })
+}
+
+fn spurious_failure() -> bool {
+    CURRENT.with(|current| match &*current.borrow() {
+        Some((scheduler, _)) => {
+            let mut state = scheduler.state.lock().unwrap();
+            if state.spurious_failures == MAX_SPURIOUS_FAILURES {
+                return false;
+            }
+            let fail = state.schedule.choose(2) == 1;
+            state.spurious_failures += fail as usize;
+            fail
+        }
+        None => false,
+    })
+}
+
+/// Atomic which lets model checker switch threads before each operation.
+///
+/// Memory orderings are ignored, so only sequentially consistent
+/// executions are explored.
+pub struct ModelAtomicUsize {
+    value: AtomicUsize,
+}
+
+impl ModelAtomicUsize {
+    /// Read the value without creating a scheduling point, to check invariants.
+    pub fn peek(&self) -> usize {
+        self.value.load(Ordering::SeqCst)
+    }
+}
+
+impl AtomicUsizeOps for ModelAtomicUsize {
+    fn new(value: usize) -> Self {
+        ModelAtomicUsize {
+            value: AtomicUsize::new(value),
+        }
+    }
+
+    fn load(&self, _order: Ordering) -> usize {
+        yield_point();
+        self.value.load(Ordering::SeqCst)
+    }
+
+    fn fetch_add(&self, value: usize, _order: Ordering) -> usize {
+        yield_point();
+        self.value.fetch_add(value, Ordering::SeqCst)
+    }
+
+    fn fetch_sub(&self, value: usize, _order: Ordering) -> usize {
+        yield_point();
+        self.value.fetch_sub(value, Ordering::SeqCst)
+    }
+
+    fn compare_exchange_weak(
+        &self,
+        current: usize,
+        new: usize,
+        _success: Ordering,
+        _failure: Ordering,
+    ) -> Result<usize, usize> {
+        yield_point();
+        let value = self.value.load(Ordering::SeqCst);
+        if value != current || spurious_failure() {
+            return Err(value);
+        }
+        self.value.store(new, Ordering::SeqCst);
+        Ok(value)
+    }
+}
+
+/// Run `thread(&state, i)` for each `i in 0..n_threads` concurrently,
+/// exploring every interleaving of [`ModelAtomicUsize`] operations.
+/// `check` is called with final state after each execution.
+///
+/// Interleavings are sequentially consistent: orderings passed to operations
+/// are ignored, so bugs that need weak memory (e.g. `Relaxed` where `Acquire`
+/// is required) are not found.
+///
+/// Panics if any thread or `check` panics. Returns the number of explored executions.
+pub fn model_check<S: Sync>(
+    n_threads: usize,
+    new_state: impl Fn() -> S,
+    thread: impl Fn(&S, usize) + Sync,
+    check: impl Fn(&S),
+) -> u64 {
+    let thread = &thread;
+    let mut schedule = Schedule::default();
+    let mut executions = 0;
+    loop {
+        let state = new_state();
+        let state = &state;
+        let scheduler = Arc::new(Scheduler {
+            state: Mutex::new(SchedulerState {
+                schedule,
+                threads: vec![ThreadState::NotStarted; n_threads],
+                spurious_failures: 0,
+                panic: None,
+            }),
+            cond: Condvar::new(),
+        });
+        thread::scope(|s| {
+            for i in 0..n_threads {
+                let scheduler = scheduler.clone();
+                s.spawn(move || {
+                    drop(scheduler.wait_turn(scheduler.state.lock().unwrap(), i));
+                    CURRENT.with(|current| *current.borrow_mut() = Some((scheduler.clone(), i)));
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| thread(state, i)));
+                    CURRENT.with(|current| *current.borrow_mut() = None);
+                    scheduler.finish(i, result.err());
+                });
+            }
+            let mut scheduler_state = scheduler.state.lock().unwrap();
+            scheduler_state.switch();
+            scheduler.cond.notify_all();
+        });
+
+        executions += 1;
+        let mut scheduler_state = scheduler.state.lock().unwrap();
+        if let Some(panic) = scheduler_state.panic.take() {
+            eprintln!(
+                "model check failed at execution {}, schedule: {:?}",
+                executions, scheduler_state.schedule.choices
+            );
+            panic::resume_unwind(panic);
+        }
+        check(state);
+        schedule = mem::take(&mut scheduler_state.schedule);
+        if !schedule.advance() {
+            return executions;
+        }
+    }
+}
@@ -1,3 +1,6 @@
+use std::cmp;
+use std::ops::RangeInclusive;
+
 use rand::Rng;
 
 pub fn random_string(len: usize) -> String {
@@ -8,3 +11,259 @@ pub fn random_string(len: usize) -> String {
     }
     s
 }
+
+/// Random char which takes `len` bytes in UTF-8.
+pub fn random_char_of_utf8_len(len: usize) -> char {
+    let range = match len {
+        1 => 0..0x80,
+        2 => 0x80..0x800,
+        3 => 0x800..0x10000,
+        4 => 0x10000..0x110000,
+        _ => panic!("invalid UTF-8 length: {}", len),
+    };
+    let mut rng = rand::thread_rng();
+    loop {
+        // Retry surrogates.
+        if let Some(c) = char::from_u32(rng.gen_range(range.clone())) {
+            return c;
+        }
+    }
+}
+
+/// Random string of `len` chars, with 1, 2, 3 and 4 byte chars equally likely.
+pub fn random_utf8_string(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| random_char_of_utf8_len(rng.gen_range(1..=4)))
+        .collect()
+}
+
+/// Random string of `len` chars from the whole ASCII range, control chars included.
+pub fn random_ascii_string(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len).map(|_| rng.gen_range('\0'..='\x7f')).collect()
+}
+
+/// `count` strings of up to `max_len` chars, each generated by `gen`,
+/// [`random_utf8_string`] or [`random_ascii_string`] with equal probability.
+pub fn mixed_strings(
+    count: usize,
+    max_len: usize,
+    mut gen: impl FnMut(usize) -> String,
+) -> Vec<String> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let len = rng.gen_range(0..=max_len);
+            match rng.gen_range(0..3) {
+                0 => gen(len),
+                1 => random_utf8_string(len),
+                _ => random_ascii_string(len),
+            }
+        })
+        .collect()
+}
+
+/// Chars which contain byte `b` in their UTF-8 encoding.
+fn chars_containing_byte(b: u8) -> Vec<char> {
+    // Sample every 7th code point to keep the pool small.
+    (0..0x110000)
+        .step_by(7)
+        .filter_map(char::from_u32)
+        .filter(|c| c.encode_utf8(&mut [0; 4]).as_bytes().contains(&b))
+        .collect()
+}
+
+/// Strings which are likely to confuse byte-level search for `needle`.
+///
+/// Strings are built from the needle itself, chars which share bytes with
+/// needle UTF-8 encoding or with `needle as u8`, and ASCII letters.
+pub fn adversarial_strings(needle: char, count: usize) -> Vec<String> {
+    let mut pool = vec![needle, 'a', 'z'];
+    let mut bytes = needle.encode_utf8(&mut [0; 4]).as_bytes().to_vec();
+    bytes.push(needle as u32 as u8);
+    bytes.sort();
+    bytes.dedup();
+    for b in bytes {
+        pool.extend(
+            chars_containing_byte(b)
+                .into_iter()
+                .filter(|&c| c != needle),
+        );
+    }
+
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|_| {
+            let len = rng.gen_range(0..=10);
+            let mut s: String = (0..len)
+                .map(|_| match rng.gen_range(0..4) {
+                    // Needle should be rare enough to be found after other chars.
+                    0 => pool[rng.gen_range(0..3)],
+                    _ => pool[rng.gen_range(0..pool.len())],
+                })
+                .collect();
+            if rng.gen_range(0..4) == 0 {
+                s.push(needle);
+            }
+            s
+        })
+        .collect()
+}
+
+/// Alphabets for generating haystacks with chars of different UTF-8 lengths.
+#[derive(Copy, Clone, Debug)]
+pub enum Script {
+    /// `a..=z`, 1 byte.
+    Ascii,
+    /// `а..=я`, 2 bytes.
+    Cyrillic,
+    /// CJK unified ideographs, 3 bytes.
+    Cjk,
+    /// Emoticons, 4 bytes.
+    Emoji,
+}
+
+impl Script {
+    pub const ALL: [Script; 4] = [Script::Ascii, Script::Cyrillic, Script::Cjk, Script::Emoji];
+
+    pub fn random_char(self) -> char {
+        let mut rng = rand::thread_rng();
+        match self {
+            Script::Ascii => rng.gen_range('a'..='z'),
+            Script::Cyrillic => rng.gen_range('а'..='я'),
+            Script::Cjk => rng.gen_range('\u{4e00}'..='\u{9fff}'),
+            Script::Emoji => rng.gen_range('\u{1f600}'..='\u{1f64f}'),
+        }
+    }
+}
+
+/// Random string of `len` chars, each char is taken from a randomly chosen script.
+pub fn random_script_string(scripts: &[Script], len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| scripts[rng.gen_range(0..scripts.len())].random_char())
+        .collect()
+}
+
+/// Where the needle is placed in generated haystacks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NeedlePosition {
+    First,
+    Middle,
+    Last,
+    Absent,
+}
+
+/// Parameters of generated search inputs.
+#[derive(Clone, Debug)]
+pub struct SearchInputSpec {
+    /// Haystack length is uniformly distributed in this range.
+    pub len: RangeInclusive<usize>,
+    pub position: NeedlePosition,
+    /// Fraction of haystacks which contain the needle, ignored for `Absent`.
+    pub hit_rate: f64,
+    /// Let filler produce the needle too, so it may occur several times
+    /// and anywhere, also in `Absent` haystacks and misses.
+    pub repeated_needles: bool,
+}
+
+impl SearchInputSpec {
+    /// Position and hit rate combinations reported by search benchmarks.
+    pub fn cells(len: RangeInclusive<usize>) -> Vec<SearchInputSpec> {
+        let mut cells = Vec::new();
+        for (position, hit_rate) in [
+            (NeedlePosition::First, 1.0),
+            (NeedlePosition::Middle, 1.0),
+            (NeedlePosition::Last, 1.0),
+            (NeedlePosition::Middle, 0.5),
+            (NeedlePosition::Middle, 0.1),
+            (NeedlePosition::Absent, 0.0),
+        ] {
+            cells.push(SearchInputSpec {
+                len: len.clone(),
+                position,
+                hit_rate,
+                repeated_needles: false,
+            });
+        }
+        cells
+    }
+
+    pub fn name(&self) -> String {
+        let position = format!("{:?}", self.position).to_lowercase();
+        match self.position {
+            NeedlePosition::Absent => format!("pos={}", position),
+            _ => format!("pos={} hit={}%", position, self.hit_rate * 100.0),
+        }
+    }
+
+    /// Generate `count` haystacks, elements other than needle are produced by `filler`
+    /// (which is retried when it returns the needle, unless `repeated_needles` is set).
+    ///
+    /// Haystacks which should contain the needle are at least one element long.
+    pub fn gen<T: PartialEq + Clone>(
+        &self,
+        count: usize,
+        needle: &T,
+        mut filler: impl FnMut() -> T,
+    ) -> Vec<Vec<T>> {
+        let mut rng = rand::thread_rng();
+        (0..count)
+            .map(|_| {
+                // Empty haystacks can't contain the needle and would lower the hit rate.
+                let min_len = match self.position {
+                    NeedlePosition::Absent => *self.len.start(),
+                    _ => cmp::max(*self.len.start(), 1),
+                };
+                let len = rng.gen_range(min_len..=*self.len.end());
+                let mut haystack: Vec<T> = (0..len)
+                    .map(|_| loop {
+                        let x = filler();
+                        if self.repeated_needles || &x != needle {
+                            break x;
+                        }
+                    })
+                    .collect();
+                let pos = match self.position {
+                    NeedlePosition::First => 0,
+                    NeedlePosition::Middle => len / 2,
+                    NeedlePosition::Last => len - 1,
+                    NeedlePosition::Absent => return haystack,
+                };
+                if rng.gen_bool(self.hit_rate) {
+                    haystack[pos] = needle.clone();
+                }
+                haystack
+            })
+            .collect()
+    }
+
+    /// Generate `count` strings, `filler` produces chars other than needle.
+    pub fn gen_strings(
+        &self,
+        count: usize,
+        needle: char,
+        filler: impl FnMut() -> char,
+    ) -> Vec<String> {
+        self.gen(count, &needle, filler)
+            .into_iter()
+            .map(|chars| chars.into_iter().collect())
+            .collect()
+    }
+}
+
+/// Random ASCII letters in random case.
+pub fn random_mixed_case_string(len: usize) -> String {
+    let mut rng = rand::thread_rng();
+    (0..len)
+        .map(|_| {
+            let c = rng.gen_range(b'a'..=b'z');
+            if rng.gen() {
+                c.to_ascii_uppercase() as char
+            } else {
+                c as char
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::mem;
+use std::ops::Deref;
+use std::ptr;
+use std::slice;
+use std::str;
+
+/// Strings up to this length are stored inline.
+pub const INLINE_CAPACITY: usize = 23;
+
+/// Value of the last byte for heap strings, inline strings store length there.
+const HEAP_TAG: u8 = 0xff;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Heap {
+    ptr: *mut u8,
+    len: usize,
+    _pad: [u8; INLINE_CAPACITY - 2 * mem::size_of::<usize>()],
+    tag: u8,
+}
+
+#[repr(C)]
+union Repr {
+    /// Bytes of the string, padded with zeros, then the length.
+    inline: [u8; INLINE_CAPACITY + 1],
+    heap: Heap,
+}
+
+/// Immutable string of 24 bytes: up to 23 bytes inline,
+/// longer strings in a heap allocated `Box<str>`.
+pub struct SmallString {
+    repr: Repr,
+}
+
+const _: () = assert!(mem::size_of::<SmallString>() == INLINE_CAPACITY + 1);
+
+unsafe impl Send for SmallString {}
+unsafe impl Sync for SmallString {}
+
+impl SmallString {
+    pub fn new(s: &str) -> SmallString {
+        if s.len() <= INLINE_CAPACITY {
+            let mut inline = [0; INLINE_CAPACITY + 1];
+            inline[..s.len()].copy_from_slice(s.as_bytes());
+            inline[INLINE_CAPACITY] = s.len() as u8;
+            return SmallString {
+                repr: Repr { inline },
+            };
+        }
+        let boxed: Box<str> = s.into();
+        let len = boxed.len();
+        SmallString {
+            repr: Repr {
+                heap: Heap {
+                    ptr: Box::into_raw(boxed) as *mut u8,
+                    len,
+                    _pad: [0; INLINE_CAPACITY - 2 * mem::size_of::<usize>()],
+                    tag: HEAP_TAG,
+                },
+            },
+        }
+    }
+
+    #[inline]
+    fn tag(&self) -> u8 {
+        // Last byte is initialized in both representations.
+        unsafe { self.repr.inline[INLINE_CAPACITY] }
+    }
+
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.tag() != HEAP_TAG
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe {
+            if self.is_inline() {
+                str::from_utf8_unchecked(&self.repr.inline[..self.tag() as usize])
+            } else {
+                let heap = self.repr.heap;
+                str::from_utf8_unchecked(slice::from_raw_parts(heap.ptr, heap.len))
+            }
+        }
+    }
+}
+
+impl Drop for SmallString {
+    fn drop(&mut self) {
+        if !self.is_inline() {
+            unsafe {
+                let heap = self.repr.heap;
+                drop(Box::from_raw(
+                    ptr::slice_from_raw_parts_mut(heap.ptr, heap.len) as *mut str,
+                ));
+            }
+        }
+    }
+}
+
+impl Clone for SmallString {
+    fn clone(&self) -> SmallString {
+        if self.is_inline() {
+            return SmallString {
+                repr: Repr {
+                    inline: unsafe { self.repr.inline },
+                },
+            };
+        }
+        SmallString::new(self.as_str())
+    }
+}
+
+impl Deref for SmallString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for SmallString {
+    fn eq(&self, other: &SmallString) -> bool {
+        if self.is_inline() && other.is_inline() {
+            // Padding is zero, so inline strings can be compared as 24-byte arrays.
+            return unsafe { self.repr.inline == other.repr.inline };
+        }
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SmallString {}
+
+impl Hash for SmallString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for SmallString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+/// Handle of a string in a `StrArena`, 8 bytes and `Copy`.
+#[derive(Copy, Clone, Debug)]
+pub struct ArenaStr {
+    start: u32,
+    len: u32,
+}
+
+/// Strings stored back to back in one buffer, freed all at once.
+#[derive(Default)]
+pub struct StrArena {
+    buf: String,
+}
+
+impl StrArena {
+    /// Panics if the arena would grow past `u32::MAX` bytes.
+    pub fn alloc(&mut self, s: &str) -> ArenaStr {
+        let start = u32::try_from(self.buf.len()).expect("arena longer than u32::MAX");
+        let len = u32::try_from(s.len()).expect("string longer than u32::MAX");
+        start.checked_add(len).expect("arena longer than u32::MAX");
+        self.buf.push_str(s);
+        ArenaStr { start, len }
+    }
+
+    #[inline]
+    pub fn get(&self, s: ArenaStr) -> &str {
+        &self.buf[s.start as usize..(s.start + s.len) as usize]
+    }
+
+    /// Invalidates all handles, keeps the buffer.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+    }
+}